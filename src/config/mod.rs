@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS, DEFAULT_CHECKPOINT_EVERY, InputArgs, LedgerArgs,
+    ReportArgs,
+};
+
+/// The configuration file discovered in the current directory, when `--config` isn't provided.
+pub const CONFIG_FILE: &str = "little_ledger.toml";
+
+/// A configuration file, setting any option of the ledger, the reports and the input, e.g.
+///
+/// ```toml
+/// [ledger]
+/// dispute_expiry = "45days"
+/// expiry_policy = "chargeback"
+/// fees = "fees.toml"
+///
+/// [report]
+/// rejects = "rejects.csv"
+///
+/// [input]
+/// merge = "timestamp"
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConfigFile {
+    pub ledger: LedgerArgs,
+    pub report: ReportArgs,
+    pub input: InputArgs,
+}
+
+impl ConfigFile {
+    /// Read the configuration file at `path`, or `little_ledger.toml` in the current directory
+    ///
+    /// Without `path`, a missing `little_ledger.toml` is an empty configuration. Relative paths in
+    /// the file are resolved against its directory.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the file can't be read or parsed.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let path = match path {
+            Some(path) => path.to_owned(),
+            None if Path::new(CONFIG_FILE).exists() => PathBuf::from(CONFIG_FILE),
+            None => return Ok(Self::default()),
+        };
+        let contents = fs::read_to_string(&path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut config: Self = toml::from_str(&contents)
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        config.resolve_paths(path.parent().unwrap_or(Path::new("")));
+        Ok(config)
+    }
+
+    /// Resolve the relative paths of the file against `dir`
+    fn resolve_paths(&mut self, dir: &Path) {
+        let paths = [
+            &mut self.ledger.fees,
+            &mut self.ledger.credit_limits,
+            &mut self.ledger.risk_limits,
+            &mut self.report.rejects,
+            &mut self.report.alerts,
+            &mut self.input.dialect,
+            &mut self.input.wal,
+        ];
+        for path in paths.into_iter().flatten() {
+            *path = dir.join(&*path);
+        }
+    }
+
+    /// Fill in the default of every option that has one, to show the effective configuration
+    #[must_use]
+    pub fn with_defaults(mut self) -> Self {
+        self.ledger.expiry_policy = Some(self.ledger.expiry_policy.unwrap_or_default());
+        self.ledger.partial_disputes = Some(self.ledger.partial_disputes.unwrap_or_default());
+        self.report.verbose = Some(self.report.verbose.unwrap_or_default());
+        self.report.dispute_counts = Some(self.report.dispute_counts.unwrap_or_default());
+        self.input.sorted = Some(self.input.sorted.unwrap_or_default());
+        self.ledger.alert_after_rejected_withdrawals = Some(
+            self.ledger
+                .alert_after_rejected_withdrawals
+                .unwrap_or(DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS),
+        );
+        self.input.merge = Some(self.input.merge.unwrap_or_default());
+        self.input.checkpoint_every = Some(
+            self.input
+                .checkpoint_every
+                .unwrap_or(DEFAULT_CHECKPOINT_EVERY),
+        );
+        self
+    }
+}
+
+impl LedgerArgs {
+    /// Override the options of the configuration `file` with the ones set on the command line
+    #[must_use]
+    pub fn or(self, file: Self) -> Self {
+        Self {
+            // Either window on the command line replaces the file's, rather than conflict with it
+            dispute_window: if self.dispute_window_duration.is_some() {
+                self.dispute_window
+            } else {
+                self.dispute_window.or(file.dispute_window)
+            },
+            dispute_window_duration: if self.dispute_window.is_some() {
+                self.dispute_window_duration
+            } else {
+                self.dispute_window_duration
+                    .or(file.dispute_window_duration)
+            },
+            dispute_expiry: self.dispute_expiry.or(file.dispute_expiry),
+            expiry_policy: self.expiry_policy.or(file.expiry_policy),
+            partial_disputes: self.partial_disputes.or(file.partial_disputes),
+            fees: self.fees.or(file.fees),
+            credit_limits: self.credit_limits.or(file.credit_limits),
+            risk_limits: self.risk_limits.or(file.risk_limits),
+            block_new_accounts: self.block_new_accounts.or(file.block_new_accounts),
+            flag_round_deposits: self.flag_round_deposits.or(file.flag_round_deposits),
+            alert_after_rejected_withdrawals: self
+                .alert_after_rejected_withdrawals
+                .or(file.alert_after_rejected_withdrawals),
+        }
+    }
+}
+
+impl ReportArgs {
+    /// Override the options of the configuration `file` with the ones set on the command line
+    #[must_use]
+    pub fn or(self, file: Self) -> Self {
+        Self {
+            verbose: self.verbose.or(file.verbose),
+            dispute_counts: self.dispute_counts.or(file.dispute_counts),
+            rejects: self.rejects.or(file.rejects),
+            alerts: self.alerts.or(file.alerts),
+        }
+    }
+}
+
+impl InputArgs {
+    /// Override the options of the configuration `file` with the ones set on the command line
+    #[must_use]
+    pub fn or(self, file: Self) -> Self {
+        Self {
+            merge: self.merge.or(file.merge),
+            dialect: self.dialect.or(file.dialect),
+            sorted: self.sorted.or(file.sorted),
+            wal: self.wal.or(file.wal),
+            checkpoint_every: self.checkpoint_every.or(file.checkpoint_every),
+        }
+    }
+}
+
+/// (De)serialize an optional duration in a human readable form, e.g. `45days`
+pub mod duration {
+    use chrono::TimeDelta;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer, de::Error};
+
+    use crate::parse_duration;
+
+    #[expect(clippy::ref_option, reason = "serde serializes fields by reference")]
+    pub fn serialize<S: Serializer>(
+        duration: &Option<TimeDelta>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        duration.map(format).serialize(serializer)
+    }
+
+    /// Format in the largest unit the duration is a whole number of, e.g. 45days rather than 1080h
+    ///
+    /// Durations with a fraction of a second are formatted in full, e.g. 1s 500ms.
+    pub(super) fn format(duration: TimeDelta) -> String {
+        if duration.subsec_nanos() != 0
+            && let Ok(duration) = duration.to_std()
+        {
+            return humantime::format_duration(duration).to_string();
+        }
+        let seconds = duration.num_seconds();
+        let (count, unit) = [(86_400, "days"), (3_600, "h"), (60, "m")]
+            .into_iter()
+            .find(|(length, _)| seconds % length == 0)
+            .map_or((seconds, "s"), |(length, unit)| (seconds / length, unit));
+        format!("{count}{unit}")
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<TimeDelta>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|s| parse_duration(&s).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+#[cfg(test)]
+mod test;
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+
+use chrono::TimeDelta;
+use clap::Parser;
+use little_ledger::{io::merge::MergeStrategy, model::ledger::ExpiryPolicy};
+
+use super::{ConfigFile, duration};
+use crate::{Cli, DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS, DEFAULT_CHECKPOINT_EVERY};
+
+#[test]
+fn load_config_file_relative_to_its_directory() {
+    let dir = std::env::temp_dir().join(format!("little_ledger_{}_config", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("writable temp dir");
+    let path = dir.join("little_ledger.toml");
+    std::fs::write(
+        &path,
+        r#"
+        [ledger]
+        dispute_expiry = "45days"
+        fees = "fees.toml"
+
+        [report]
+        verbose = true
+        rejects = "/var/log/rejects.csv"
+
+        [input]
+        merge = "timestamp"
+        "#,
+    )
+    .expect("writable config");
+
+    let config = ConfigFile::load(Some(&path)).expect("valid config");
+    assert_eq!(config.ledger.dispute_expiry, Some(TimeDelta::days(45)));
+    assert_eq!(config.ledger.fees, Some(dir.join("fees.toml")));
+    assert_eq!(
+        config.report.rejects,
+        Some(PathBuf::from("/var/log/rejects.csv"))
+    );
+    assert_eq!(config.report.verbose, Some(true));
+    assert_eq!(config.input.merge, Some(MergeStrategy::Timestamp));
+
+    std::fs::write(&path, "[ledger]\nverbose = true\n").expect("writable config");
+    ConfigFile::load(Some(&path)).expect_err("verbose is a report option");
+    ConfigFile::load(Some(&dir.join("missing.toml"))).expect_err("an explicit file must exist");
+
+    std::fs::remove_dir_all(dir).expect("removable temp dir");
+}
+
+#[test]
+fn command_line_overrides_config_file() {
+    let file: ConfigFile = toml::from_str(
+        r#"
+        [ledger]
+        dispute_window_duration = "90days"
+        partial_disputes = true
+        fees = "fees.toml"
+
+        [report]
+        verbose = true
+        dispute_counts = true
+
+        [input]
+        sorted = true
+        "#,
+    )
+    .expect("valid config");
+    let cli = Cli::try_parse_from([
+        "little_ledger",
+        "--dispute-window",
+        "10",
+        "--credit-limits",
+        "limits.csv",
+        "--verbose=false",
+        "--dispute-counts",
+        "--sorted=false",
+        "input.csv",
+    ])
+    .expect("valid arguments");
+    assert_eq!(cli.input_paths, ["input.csv"]);
+
+    let ledger = cli.ledger.or(file.ledger);
+    assert_eq!(ledger.dispute_window, Some(10));
+    assert_eq!(ledger.dispute_window_duration, None);
+    assert_eq!(ledger.fees, Some(PathBuf::from("fees.toml")));
+    assert_eq!(ledger.credit_limits, Some(PathBuf::from("limits.csv")));
+    assert_eq!(ledger.partial_disputes, Some(true));
+    let report = cli.report.or(file.report);
+    assert_eq!(report.verbose, Some(false));
+    assert_eq!(report.dispute_counts, Some(true));
+    assert_eq!(cli.input.or(file.input).sorted, Some(false));
+
+    // A switch turned off doesn't conflict with the options it can't be used with
+    Cli::try_parse_from([
+        "little_ledger",
+        "--sorted=false",
+        "--since",
+        "2024-01-01T00:00:00Z",
+    ])
+    .expect("--since can be used unsorted");
+}
+
+#[test]
+fn config_defaults_are_filled_in() {
+    let config = ConfigFile::default().with_defaults();
+    assert_eq!(config.ledger.expiry_policy, Some(ExpiryPolicy::Resolve));
+    assert_eq!(
+        config.ledger.alert_after_rejected_withdrawals,
+        Some(DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS)
+    );
+    assert_eq!(config.input.merge, Some(MergeStrategy::Concat));
+    assert_eq!(
+        config.input.checkpoint_every,
+        Some(DEFAULT_CHECKPOINT_EVERY)
+    );
+    assert_eq!(config.ledger.dispute_window, None);
+    assert_eq!(config.report.verbose, Some(false));
+
+    let shown = toml::to_string_pretty(&config).expect("serializable config");
+    let parsed: ConfigFile = toml::from_str(&shown).expect("the shown config can be read back");
+    assert_eq!(parsed.input.merge, Some(MergeStrategy::Concat));
+}
+
+#[test]
+fn format_durations_without_losing_precision() {
+    assert_eq!(duration::format(TimeDelta::days(45)), "45days");
+    assert_eq!(duration::format(TimeDelta::minutes(90)), "90m");
+    assert_eq!(duration::format(TimeDelta::seconds(61)), "61s");
+    assert_eq!(duration::format(TimeDelta::milliseconds(500)), "500ms");
+    assert_eq!(duration::format(TimeDelta::milliseconds(1500)), "1s 500ms");
+}
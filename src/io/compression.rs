@@ -0,0 +1,118 @@
+use std::{
+    fs::File,
+    io::{self, BufRead, BufWriter, Read, Stdout, Write},
+    path::Path,
+};
+
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
+
+/// How an input or output is compressed
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum Compression {
+    #[default]
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Guess from the extension of a path, i.e. `.gz` or `.zst`
+    #[must_use]
+    pub fn from_extension(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+
+    /// Detect from the magic bytes at the start of a stream
+    #[must_use]
+    pub fn from_magic(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&[0x1f, 0x8b]) {
+            Compression::Gzip
+        } else if bytes.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+}
+
+/// Decompress an input, if its magic bytes say it's compressed
+///
+/// Inputs are detected by content rather than by extension, so compressed stdin works too.
+///
+/// # Errors
+///
+/// Returns `io::Error` if the start of the input can't be read, or the decoder can't be created.
+pub fn decompress<'a, R: BufRead + 'a>(mut rdr: R) -> io::Result<Box<dyn Read + 'a>> {
+    Ok(match Compression::from_magic(rdr.fill_buf()?) {
+        Compression::None => Box::new(rdr),
+        Compression::Gzip => Box::new(MultiGzDecoder::new(rdr)),
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(rdr)?),
+    })
+}
+
+/// A writable output, that may have to be finished once fully written
+///
+/// Compressed outputs are finished by writing the end of their stream, e.g. the gzip trailer,
+/// other outputs are only flushed. Nothing should be written to an output once finished.
+pub trait Output: Write + Send {
+    /// Flush everything written so far, and finish the output
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::Error` if the output can't be written.
+    fn finish(&mut self) -> io::Result<()> {
+        self.flush()
+    }
+}
+
+impl<O: Output + ?Sized> Output for Box<O> {
+    fn finish(&mut self) -> io::Result<()> {
+        (**self).finish()
+    }
+}
+
+impl Output for Stdout {}
+
+impl Output for File {}
+
+impl Output for Vec<u8> {}
+
+impl Output for &mut Vec<u8> {}
+
+impl<W: Write + Send> Output for BufWriter<W> {}
+
+impl<W: Write + Send> Output for GzEncoder<W> {
+    fn finish(&mut self) -> io::Result<()> {
+        self.try_finish()?;
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write + Send> Output for zstd::Encoder<'_, W> {
+    fn finish(&mut self) -> io::Result<()> {
+        self.do_finish()?;
+        self.get_mut().flush()
+    }
+}
+
+/// Compress an output as per `compression`
+///
+/// The compressed stream is only complete once the output is finished, see `Output::finish`.
+///
+/// # Errors
+///
+/// Returns `io::Error` if the encoder can't be created.
+pub fn compress<'a, W: Output + 'a>(
+    wtr: W,
+    compression: Compression,
+) -> io::Result<Box<dyn Output + 'a>> {
+    Ok(match compression {
+        Compression::None => Box::new(wtr),
+        Compression::Gzip => Box::new(GzEncoder::new(wtr, flate2::Compression::default())),
+        Compression::Zstd => Box::new(zstd::Encoder::new(wtr, 0)?),
+    })
+}
@@ -0,0 +1,181 @@
+use std::{collections::HashMap, io::Read};
+
+use anyhow::{Result, bail};
+use csv::{Reader, StringRecord};
+use serde::Deserialize;
+
+use super::{input::TransactionType, line::DEFAULT_HEADERS};
+
+/// How a partner's CSV maps onto the input columns
+///
+/// The default dialect is the one of the input CSV: comma delimited, double quoted, with a header
+/// row naming the `type,client,tx,amount,timestamp,to` columns, and decimal points. A dialect is
+/// read from TOML, e.g.
+///
+/// ```toml
+/// delimiter = ";"
+/// decimal_separator = ","
+///
+/// [columns]
+/// type = "kind"
+/// client = "customer_id"
+/// tx = "txid"
+///
+/// [types]
+/// credit = "deposit"
+/// debit = "withdrawal"
+/// ```
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Dialect {
+    /// The field delimiter, an ASCII character
+    pub delimiter: char,
+    /// The quote character, an ASCII character
+    pub quote: char,
+    /// Whether fields can be quoted at all
+    pub quoting: bool,
+    /// Whether the first row is a header, naming the columns
+    pub has_headers: bool,
+    /// The columns of inputs without a header row, in order, `type,client,tx,amount,timestamp,to`
+    /// by default
+    pub headers: Option<Vec<String>>,
+    /// The decimal separator of amounts, either `.` or `,`
+    pub decimal_separator: char,
+    /// The partner's name of each input column, if it differs
+    pub columns: Columns,
+    /// The partner's names of transaction types, e.g. `credit = "deposit"`
+    pub types: HashMap<String, TransactionType>,
+}
+
+/// The partner's name of each input column, any column left out keeps its own name
+#[derive(Debug, PartialEq, Eq, Clone, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Columns {
+    #[serde(rename = "type")]
+    pub transaction_type: Option<String>,
+    pub client: Option<String>,
+    pub tx: Option<String>,
+    pub amount: Option<String>,
+    pub timestamp: Option<String>,
+    pub to: Option<String>,
+}
+
+impl Default for Dialect {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            quote: '"',
+            quoting: true,
+            has_headers: true,
+            headers: None,
+            decimal_separator: '.',
+            columns: Columns::default(),
+            types: HashMap::new(),
+        }
+    }
+}
+
+impl Dialect {
+    /// Check that the delimiter, the quote and the decimal separator can be used
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the delimiter or the quote is not ASCII, or the decimal
+    /// separator is neither `.` nor `,`.
+    pub fn validate(&self) -> Result<()> {
+        if !self.delimiter.is_ascii() {
+            bail!("delimiter {:?} is not an ASCII character", self.delimiter);
+        }
+        if !self.quote.is_ascii() {
+            bail!("quote {:?} is not an ASCII character", self.quote);
+        }
+        if !matches!(self.decimal_separator, '.' | ',') {
+            bail!(
+                "unknown decimal separator {:?}, expected . or ,",
+                self.decimal_separator
+            );
+        }
+        if self.decimal_separator == self.delimiter && !self.quoting {
+            bail!("decimal commas need quoting, as commas delimit fields");
+        }
+        Ok(())
+    }
+
+    /// Create a CSV reader in this dialect
+    ///
+    /// Fields are trimmed, and rows can have fewer fields than the header, as in the input CSV.
+    pub fn reader<R: Read>(&self, rdr: R) -> Reader<R> {
+        let ascii = |c: char| u8::try_from(c).ok().filter(u8::is_ascii);
+        let mut builder = csv::ReaderBuilder::new();
+        builder
+            .trim(csv::Trim::All)
+            .has_headers(self.has_headers)
+            .flexible(true)
+            .quoting(self.quoting);
+        if let Some(delimiter) = ascii(self.delimiter) {
+            builder.delimiter(delimiter);
+        }
+        if let Some(quote) = ascii(self.quote) {
+            builder.quote(quote);
+        }
+        builder.from_reader(rdr)
+    }
+
+    /// Get the input columns of a reader in this dialect
+    ///
+    /// Reads the header row, if any, and renames the partner's columns to the input ones.
+    ///
+    /// # Errors
+    ///
+    /// Returns `csv::Error` if the header row can't be read.
+    pub fn headers<R: Read>(&self, rdr: &mut Reader<R>) -> csv::Result<StringRecord> {
+        let headers = if self.has_headers {
+            rdr.headers()?.clone()
+        } else {
+            match &self.headers {
+                Some(headers) => StringRecord::from(headers.clone()),
+                None => StringRecord::from(DEFAULT_HEADERS.to_vec()),
+            }
+        };
+        let renames = [
+            (&self.columns.transaction_type, "type"),
+            (&self.columns.client, "client"),
+            (&self.columns.tx, "tx"),
+            (&self.columns.amount, "amount"),
+            (&self.columns.timestamp, "timestamp"),
+            (&self.columns.to, "to"),
+        ];
+        Ok(headers
+            .iter()
+            .map(|header| {
+                renames
+                    .iter()
+                    .find(|(partner, _)| partner.as_deref() == Some(header))
+                    .map_or(header, |(_, column)| column)
+            })
+            .collect())
+    }
+
+    /// Rewrite the fields of a row the input columns can't read as is
+    ///
+    /// Aliased transaction types are replaced by their own name, and decimal commas by points.
+    pub fn normalize(&self, record: &mut StringRecord, headers: &StringRecord) {
+        if self.types.is_empty() && self.decimal_separator == '.' {
+            return;
+        }
+        let position = record.position().cloned();
+        *record = record
+            .iter()
+            .zip(headers.iter())
+            .map(|(field, header)| match header {
+                "type" => self
+                    .types
+                    .get(field)
+                    .map_or_else(|| field.to_owned(), |t| t.name().to_owned()),
+                "amount" => field.replace(self.decimal_separator, "."),
+                _ => field.to_owned(),
+            })
+            .collect();
+        record.set_position(position);
+    }
+}
@@ -0,0 +1,139 @@
+use std::{
+    collections::HashMap,
+    hash::BuildHasher,
+    io::{Read, Write},
+};
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use super::{input::reader, output::writer};
+use crate::model::common::{Amount, Client};
+
+/// The columns of a ledger output compared by `diff_ledgers`, any other column is ignored
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+pub struct LedgerRecord {
+    pub client: Client,
+    pub available: Decimal,
+    pub held: Decimal,
+    pub total: Decimal,
+    pub locked: bool,
+}
+
+/// A difference between two ledger outputs
+///
+/// Added and removed clients have no `field`, `old` nor `new` value.
+#[derive(Debug, PartialEq, Eq, Clone, Serialize)]
+pub struct Difference {
+    pub client: Client,
+    pub change: Change,
+    pub field: Option<&'static str>,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+/// How a client differs between two ledger outputs
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Change {
+    /// The client is only in the new output
+    Added,
+    /// The client is only in the old output
+    Removed,
+    /// A field of the client differs
+    Changed,
+}
+
+/// Read a ledger output, by client
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if any row can't be read, as a partial ledger would report bogus
+/// differences.
+pub fn read_ledger<R: Read>(rdr: R) -> Result<HashMap<Client, LedgerRecord>> {
+    reader(rdr)
+        .into_deserialize::<LedgerRecord>()
+        .enumerate()
+        .map(|(i, record)| {
+            let record = record.with_context(|| format!("invalid ledger row {}", i + 1))?;
+            Ok((record.client, record))
+        })
+        .collect()
+}
+
+/// Compare two ledger outputs
+///
+/// Amounts that differ by no more than `tolerance` are considered equal. Differences are
+/// returned by client, then in column order.
+#[must_use]
+pub fn diff_ledgers<S: BuildHasher>(
+    old: &HashMap<Client, LedgerRecord, S>,
+    new: &HashMap<Client, LedgerRecord, S>,
+    tolerance: Amount,
+) -> Vec<Difference> {
+    let mut clients: Vec<Client> = old.keys().chain(new.keys()).copied().collect();
+    clients.sort_unstable_by_key(|client| client.0);
+    clients.dedup();
+
+    let mut differences = Vec::new();
+    for client in clients {
+        let (old, new) = match (old.get(&client), new.get(&client)) {
+            (Some(old), Some(new)) => (old, new),
+            (old, _) => {
+                differences.push(Difference {
+                    client,
+                    change: if old.is_some() {
+                        Change::Removed
+                    } else {
+                        Change::Added
+                    },
+                    field: None,
+                    old: None,
+                    new: None,
+                });
+                continue;
+            }
+        };
+
+        let mut changed = |field, old: String, new: String| {
+            differences.push(Difference {
+                client,
+                change: Change::Changed,
+                field: Some(field),
+                old: Some(old),
+                new: Some(new),
+            });
+        };
+        for (field, old, new) in [
+            ("available", old.available, new.available),
+            ("held", old.held, new.held),
+            ("total", old.total, new.total),
+        ] {
+            // A difference too large to be computed is a difference all the same
+            let differs = old.checked_sub(new).is_none_or(|delta| {
+                Amount::try_from(delta.abs()).is_ok_and(|delta| delta > tolerance)
+            });
+            if differs {
+                changed(field, old.to_string(), new.to_string());
+            }
+        }
+        if old.locked != new.locked {
+            changed("locked", old.locked.to_string(), new.locked.to_string());
+        }
+    }
+    differences
+}
+
+/// Write the differences between two ledger outputs as CSV
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if a difference can't be written, or the buffer can't be flushed.
+pub fn write_differences<W: Write>(wtr: W, differences: &[Difference]) -> Result<()> {
+    let mut wtr = writer(wtr);
+    for difference in differences {
+        wtr.serialize(difference)?;
+    }
+    wtr.flush().with_context(|| "failed to flush the buffer")
+}
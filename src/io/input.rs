@@ -1,6 +1,7 @@
 use std::{
+    collections::HashMap,
     io::{BufReader, Read, stdin},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use csv::Reader;
@@ -8,30 +9,48 @@ use rust_decimal::Decimal;
 use serde::Deserialize;
 use thiserror::Error;
 
+use super::{compression::decompress, dialect::Dialect};
 use crate::model::{
-    common::{Amount, Client, Tx},
-    transaction::{Deposit, DisputeStatus, Transaction, Type, Withdrawal},
+    common::{Amount, Client, Timestamp, Tx},
+    fee::FeeSchedule,
+    risk::RiskConfig,
+    transaction::{Deposit, Transaction, Transfer, Type, Withdrawal},
 };
 
 /// Creates a file CSV reader
 ///
-/// Reads from the `input_path` or stdin if `input_path` is `None`.
+/// Reads from the `input_path` or stdin if `input_path` is `None`. Gzip and zstd inputs are
+/// decompressed on the fly, as detected by their magic bytes.
 ///
 /// # Errors
 ///
-/// Returns `anyhow::Error` if the file can not be opened.
+/// Returns `anyhow::Error` if the file can not be opened, or its start can't be read.
 pub fn create_csv_reader(
     input_path: Option<PathBuf>,
+) -> anyhow::Result<csv::Reader<Box<dyn Read>>> {
+    create_dialect_reader(input_path, &Dialect::default())
+}
+
+/// Creates a file CSV reader in a dialect
+///
+/// Same as `create_csv_reader`, for inputs in a partner's `dialect`.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can not be opened, or its start can't be read.
+pub fn create_dialect_reader(
+    input_path: Option<PathBuf>,
+    dialect: &Dialect,
 ) -> anyhow::Result<csv::Reader<Box<dyn Read>>> {
     let buf_reader: Box<dyn Read> = match input_path {
         Some(path) => {
             let file = std::fs::File::open(path)?;
-            Box::new(BufReader::new(file))
+            decompress(BufReader::new(file))?
         }
-        None => Box::new(BufReader::new(stdin())),
+        None => decompress(BufReader::new(stdin()))?,
     };
 
-    Ok(reader(buf_reader))
+    Ok(dialect.reader(buf_reader))
 }
 
 /// Deserialize transactions
@@ -45,39 +64,140 @@ pub fn deserialize_transactions(
     rdr: Option<csv::Reader<Box<dyn Read>>>,
     verbose: bool,
 ) -> anyhow::Result<impl Iterator<Item = Transaction>> {
+    deserialize_dialect_transactions(rdr, &Dialect::default(), verbose)
+}
+
+/// Deserialize transactions in a dialect
+///
+/// Same as `deserialize_transactions`, for a reader created in a partner's `dialect`.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the header row can't be read.
+pub fn deserialize_dialect_transactions(
+    rdr: Option<csv::Reader<Box<dyn Read>>>,
+    dialect: &Dialect,
+    verbose: bool,
+) -> anyhow::Result<impl Iterator<Item = Transaction> + use<>> {
     let rdr: csv::Reader<Box<dyn Read>> = match rdr {
         Some(r) => r,
-        None => reader(Box::new(BufReader::new(stdin()))),
+        None => dialect.reader(Box::new(BufReader::new(stdin()))),
     };
 
-    Ok(map_transactions(deserialize_records(rdr, verbose), verbose))
+    Ok(map_transactions(
+        deserialize_records(rdr, dialect.clone(), verbose)?,
+        verbose,
+    ))
+}
+
+/// Read a fee schedule
+///
+/// Parses the TOML file at `path`, e.g.
+///
+/// ```toml
+/// house = 0
+///
+/// [withdrawal]
+/// flat = "0.5"
+/// percent = "1"
+/// max = "10"
+/// ```
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can't be read, or is not a valid fee schedule.
+pub fn read_fee_schedule(path: &Path) -> anyhow::Result<FeeSchedule> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(toml::from_str(&content)?)
+}
+
+/// Read risk limits
+///
+/// Parses the TOML file at `path`, with optional `default` limits and per client ones, e.g.
+///
+/// ```toml
+/// [default]
+/// max_withdrawal = "1000"
+/// max_withdrawals = { count = 5, window = "1h" }
+/// max_daily_outflow = "5000"
+/// lock_on_breach = true
+///
+/// [clients.7]
+/// max_withdrawal = "10000"
+/// ```
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can't be read, or is not a valid risk configuration.
+pub fn read_risk_limits(path: &Path) -> anyhow::Result<RiskConfig> {
+    let content = std::fs::read_to_string(path)?;
+    Ok(toml::from_str(&content)?)
+}
+
+/// Read an input dialect
+///
+/// Parses the TOML file at `path`, see `Dialect` for an example.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can't be read, or is not a valid dialect.
+pub fn read_dialect(path: &Path) -> anyhow::Result<Dialect> {
+    let content = std::fs::read_to_string(path)?;
+    let dialect: Dialect = toml::from_str(&content)?;
+    dialect.validate()?;
+    Ok(dialect)
+}
+
+/// Read credit limits
+///
+/// Parses the CSV file at `path`, with a `client` and a `limit` column, e.g.
+///
+/// ```csv
+/// client,limit
+/// 1,500.0
+/// ```
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can't be opened, or any of its records is invalid.
+pub fn read_credit_limits(path: &Path) -> anyhow::Result<HashMap<Client, Amount>> {
+    let file = std::fs::File::open(path)?;
+    reader(BufReader::new(file))
+        .into_deserialize::<InputCreditLimitRecord>()
+        .map(|record| {
+            let record = record?;
+            Ok((record.client, record.limit))
+        })
+        .collect()
 }
 
-/// Generic csv reader for anything that can be `Read`
+/// Generic csv reader for anything that can be `Read`, in the default dialect
 pub fn reader<R: Read>(rdr: R) -> Reader<R> {
-    csv::ReaderBuilder::new()
-        .trim(csv::Trim::All)
-        .has_headers(true)
-        .flexible(true)
-        .from_reader(rdr)
+    Dialect::default().reader(rdr)
 }
 
 fn deserialize_records(
-    rdr: csv::Reader<Box<dyn Read>>,
+    mut rdr: csv::Reader<Box<dyn Read>>,
+    dialect: Dialect,
     verbose: bool,
-) -> impl Iterator<Item = InputTransactionRecord> {
-    rdr.into_deserialize::<InputTransactionRecord>().filter_map(
-        move |txn_record| match txn_record {
+) -> csv::Result<impl Iterator<Item = InputTransactionRecord>> {
+    let headers = dialect.headers(&mut rdr)?;
+    Ok(rdr.into_records().filter_map(move |record| {
+        let txn_record = record.and_then(|mut record| {
+            dialect.normalize(&mut record, &headers);
+            record.deserialize::<InputTransactionRecord>(Some(&headers))
+        });
+        match txn_record {
             Ok(itr) => Some(itr),
             Err(e) => {
                 let e = InputMappingError::ParseError(e);
                 if verbose {
-                    eprintln!("input_mapping_error::parse_error: {e}",);
+                    eprintln!("input_mapping_error::parse_error: {e}");
                 }
                 None
             }
-        },
-    )
+        }
+    }))
 }
 
 fn map_transactions(
@@ -103,11 +223,21 @@ pub struct InputTransactionRecord {
     transaction_type: TransactionType,
     client: Client,
     tx: Tx,
-    #[serde(with = "rust_decimal::serde::str_option")]
+    #[serde(default, with = "rust_decimal::serde::str_option")]
     amount: Option<Decimal>,
+    #[serde(default)]
+    timestamp: Option<Timestamp>,
+    #[serde(default)]
+    to: Option<Client>,
+}
+
+#[derive(Debug, Deserialize)]
+struct InputCreditLimitRecord {
+    client: Client,
+    limit: Amount,
 }
 
-#[derive(Debug, Deserialize, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Deserialize, Clone, Copy)]
 #[serde(rename_all = "lowercase")]
 pub enum TransactionType {
     Deposit,
@@ -115,6 +245,22 @@ pub enum TransactionType {
     Dispute,
     Resolve,
     Chargeback,
+    Transfer,
+}
+
+impl TransactionType {
+    /// The name of this type in the input CSV
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            TransactionType::Deposit => "deposit",
+            TransactionType::Withdrawal => "withdrawal",
+            TransactionType::Dispute => "dispute",
+            TransactionType::Resolve => "resolve",
+            TransactionType::Chargeback => "chargeback",
+            TransactionType::Transfer => "transfer",
+        }
+    }
 }
 
 #[derive(Error, Debug)]
@@ -124,6 +270,8 @@ pub enum InputMappingError {
         transaction_type: TransactionType,
         tx: Tx,
     },
+    #[error("missing mandatory recipient for a transfer - {0:?}")]
+    MissingRecipient(Tx),
     #[error("invalid amount {dec_amount} for {tx:?}")]
     InvalidAmount { dec_amount: Decimal, tx: Tx },
     #[error("line {0} could not be parsed")]
@@ -136,54 +284,40 @@ impl TryFrom<InputTransactionRecord> for Transaction {
     fn try_from(raw_record: InputTransactionRecord) -> Result<Self, Self::Error> {
         let tx = raw_record.tx;
         let client = raw_record.client;
+        let timestamp = raw_record.timestamp;
         let transaction_type = raw_record.transaction_type;
 
-        match raw_record.transaction_type {
-            TransactionType::Deposit => {
-                let dec_amount = raw_record.amount.ok_or(InputMappingError::MissingAmount {
-                    transaction_type,
-                    tx,
-                })?;
-                let amount = Amount::try_from(dec_amount)
-                    .or(Err(InputMappingError::InvalidAmount { tx, dec_amount }))?;
-                Ok(Transaction {
-                    client,
-                    tx,
-                    t_type: Type::Deposit(Deposit {
-                        dispute_status: DisputeStatus::default(),
-                        amount,
-                    }),
-                })
-            }
-            TransactionType::Withdrawal => {
-                let dec_amount = raw_record.amount.ok_or(InputMappingError::MissingAmount {
-                    transaction_type,
-                    tx,
-                })?;
-                let amount = Amount::try_from(dec_amount)
-                    .or(Err(InputMappingError::InvalidAmount { tx, dec_amount }))?;
-
-                Ok(Transaction {
-                    client,
-                    tx,
-                    t_type: Type::Withdrawal(Withdrawal { amount }),
-                })
-            }
-            TransactionType::Dispute => Ok(Transaction {
-                t_type: Type::Dispute,
-                client,
-                tx,
-            }),
-            TransactionType::Resolve => Ok(Transaction {
-                t_type: Type::Resolve,
-                client,
+        let optional_amount = raw_record.amount;
+        let mandatory_amount = || {
+            let dec_amount = optional_amount.ok_or(InputMappingError::MissingAmount {
+                transaction_type,
                 tx,
+            })?;
+            Amount::try_from(dec_amount)
+                .or(Err(InputMappingError::InvalidAmount { tx, dec_amount }))
+        };
+
+        let t_type = match transaction_type {
+            TransactionType::Deposit => Type::Deposit(Deposit::new(mandatory_amount()?)),
+            TransactionType::Withdrawal => Type::Withdrawal(Withdrawal {
+                amount: mandatory_amount()?,
             }),
-            TransactionType::Chargeback => Ok(Transaction {
-                t_type: Type::Chargeback,
-                client,
-                tx,
+            TransactionType::Dispute => Type::Dispute(optional_amount),
+            TransactionType::Resolve => Type::Resolve(optional_amount),
+            TransactionType::Chargeback => Type::Chargeback(optional_amount),
+            TransactionType::Transfer => Type::Transfer(Transfer {
+                to: raw_record
+                    .to
+                    .ok_or(InputMappingError::MissingRecipient(tx))?,
+                amount: mandatory_amount()?,
             }),
-        }
+        };
+
+        Ok(Transaction {
+            t_type,
+            client,
+            tx,
+            timestamp,
+        })
     }
 }
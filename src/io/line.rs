@@ -0,0 +1,237 @@
+use std::{
+    io::{self, BufRead, BufReader, Read, Write},
+    sync::mpsc::{self, Receiver, Sender},
+    thread::{self, JoinHandle},
+};
+
+use anyhow::Result;
+use csv::StringRecord;
+
+use super::{
+    input::{InputMappingError, InputTransactionRecord},
+    output::{OutputColumns, Reporter, serialize_accounts},
+};
+use crate::model::{
+    account::{AccountLockedError, DisputeWindowExpiredError, NonMonotonicTimestampError},
+    balance::BalanceError,
+    common::AmountError,
+    ledger::Ledger,
+    risk::RiskLimitError,
+    transaction::Transaction,
+    validator::RejectedError,
+};
+
+/// The columns of a line, unless a connection sends its own header first
+pub const DEFAULT_HEADERS: [&str; 6] = ["type", "client", "tx", "amount", "timestamp", "to"];
+
+/// A line protocol over a ledger kept in memory, for any number of connections
+///
+/// Each line is either a CSV row of a transaction, acknowledged with `ok` once applied or with
+/// `error <kind>: <reason>` otherwise, or the `dump` command, answered with the ledger in the
+/// output CSV format followed by `ok`.
+///
+/// Rows have the columns `type,client,tx,amount,timestamp,to` by default, trailing ones can be
+/// left out. A connection can send its own header line first, i.e. a row starting with `type`.
+///
+/// The ledger is owned by a single thread, see `serve`, while each connection is read in its own
+/// thread, see `accept`.
+pub struct LineServer<'a> {
+    pub ledger: Ledger,
+    pub columns: OutputColumns,
+    pub reporter: Reporter<'a>,
+}
+
+/// What a connection asks the ledger to do
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Command {
+    /// Apply a transaction
+    Apply(Transaction),
+    /// Dump the ledger
+    Dump,
+    /// Stop serving, e.g. once the process is interrupted
+    Stop,
+}
+
+/// A command sent by a connection, along with where to send the reply
+#[derive(Debug)]
+pub struct Request {
+    pub command: Command,
+    pub reply: Sender<String>,
+}
+
+impl LineServer<'_> {
+    /// Handle the requests of every connection, in the order they are received
+    ///
+    /// Returns once a `Stop` command is received, or no more requests can be received, i.e. every
+    /// sender is dropped. The reports are flushed after each request, and their outputs finished
+    /// once done.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the reports can't be flushed once done.
+    pub fn serve(&mut self, requests: &Receiver<Request>) -> Result<()> {
+        for request in requests {
+            let stop = request.command == Command::Stop;
+            let reply = self.handle(request.command);
+            // Before replying, so the reports are up to date once the connection gets the reply
+            if let Err(e) = self.reporter.flush_buffers()
+                && self.reporter.verbose
+            {
+                eprintln!("warning: failed to flush the reports: {e}");
+            }
+            // The connection may be gone already, there's no one else to tell
+            let _ = request.reply.send(reply);
+            if stop {
+                break;
+            }
+        }
+        self.reporter.flush()
+    }
+
+    /// Handle a command, and get the reply
+    pub fn handle(&mut self, command: Command) -> String {
+        match command {
+            Command::Apply(txn) => {
+                let result = self.ledger.apply(txn);
+                let reply = match &result {
+                    Ok(()) => "ok".to_owned(),
+                    Err(e) => format!("error {}: {e}", error_kind(e)),
+                };
+                self.reporter.report(&mut self.ledger, &txn, result);
+                reply
+            }
+            Command::Dump => match self.dump() {
+                Ok(dump) => format!("{dump}ok"),
+                Err(e) => format!("error io: {e}"),
+            },
+            Command::Stop => "ok".to_owned(),
+        }
+    }
+
+    /// Write the ledger in the output CSV format, by client
+    fn dump(&self) -> Result<String> {
+        let mut dump = Vec::new();
+        serialize_accounts(&self.ledger, &mut dump, self.columns)?;
+        Ok(String::from_utf8(dump)?)
+    }
+}
+
+/// Accept connections in the background, reading each one in its own thread
+///
+/// Every line read is parsed and forwarded to `requests`, then the reply is written back. Lines
+/// that can't be parsed are answered right away, with an `error malformed`.
+pub fn accept<S, A>(mut accept: A, requests: Sender<Request>) -> JoinHandle<()>
+where
+    A: FnMut() -> io::Result<S> + Send + 'static,
+    S: Send + 'static,
+    for<'s> &'s S: Read + Write,
+{
+    thread::spawn(move || {
+        while let Ok(stream) = accept() {
+            let requests = requests.clone();
+            thread::spawn(move || connection(&stream, &requests));
+        }
+    })
+}
+
+/// Read the lines of a connection until it's closed, or the ledger is gone
+fn connection<S>(stream: &S, requests: &Sender<Request>) -> io::Result<()>
+where
+    for<'s> &'s S: Read + Write,
+{
+    let mut headers = StringRecord::from(DEFAULT_HEADERS.to_vec());
+    for line in BufReader::new(stream).lines() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let command = if line == "dump" {
+            Command::Dump
+        } else {
+            match parse_line(line, &headers) {
+                Ok(Line::Header(record)) => {
+                    headers = record;
+                    writeln!(&mut &*stream, "ok")?;
+                    continue;
+                }
+                Ok(Line::Transaction(txn)) => Command::Apply(txn),
+                Err(e) => {
+                    writeln!(&mut &*stream, "error malformed: {e}")?;
+                    continue;
+                }
+            }
+        };
+
+        let (reply, replied) = mpsc::channel();
+        if requests.send(Request { command, reply }).is_err() {
+            break;
+        }
+        let Ok(reply) = replied.recv() else {
+            break;
+        };
+        writeln!(&mut &*stream, "{reply}")?;
+    }
+    Ok(())
+}
+
+/// A parsed line
+#[derive(Debug, PartialEq)]
+pub enum Line {
+    /// The columns of the following lines
+    Header(StringRecord),
+    /// A transaction, in the current columns
+    Transaction(Transaction),
+}
+
+/// Parse a CSV line, in the `headers` columns
+///
+/// # Errors
+///
+/// Returns an `InputMappingError` if the line isn't a valid transaction.
+pub fn parse_line(line: &str, headers: &StringRecord) -> Result<Line, InputMappingError> {
+    let record = parse_record(line)?;
+    if record.get(0) == Some("type") {
+        return Ok(Line::Header(record));
+    }
+    let record: InputTransactionRecord = record.deserialize(Some(headers))?;
+    Ok(Line::Transaction(Transaction::try_from(record)?))
+}
+
+/// Parse the fields of a CSV line, trimmed, whatever they are
+///
+/// # Errors
+///
+/// Returns an `InputMappingError` if the line isn't valid CSV.
+pub fn parse_record(line: &str) -> Result<StringRecord, InputMappingError> {
+    let mut rdr = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .trim(csv::Trim::All)
+        .from_reader(line.as_bytes());
+    let mut record = StringRecord::new();
+    rdr.read_record(&mut record)?;
+    Ok(record)
+}
+
+/// Get the kind of error a transaction was rejected with, for its acknowledgement
+#[must_use]
+pub fn error_kind(e: &anyhow::Error) -> &'static str {
+    if e.is::<RejectedError>() {
+        "rejected_by_validator"
+    } else if e.is::<RiskLimitError>() {
+        "risk_limit"
+    } else if e.is::<AccountLockedError>() {
+        "locked"
+    } else if e.is::<DisputeWindowExpiredError>() {
+        "dispute_window_expired"
+    } else if e.is::<NonMonotonicTimestampError>() {
+        "out_of_order"
+    } else if e.is::<AmountError>() || e.downcast_ref() == Some(&BalanceError::Overflow) {
+        "overflow"
+    } else if e.is::<BalanceError>() {
+        "insufficient_funds"
+    } else {
+        "rejected"
+    }
+}
@@ -0,0 +1,173 @@
+use std::{
+    iter::Peekable,
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    dialect::Dialect,
+    input::{create_dialect_reader, deserialize_dialect_transactions},
+};
+use crate::model::{
+    common::{Timestamp, Tx},
+    transaction::Transaction,
+};
+
+/// How the transactions of several inputs are merged into a single stream
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeStrategy {
+    /// Every transaction of an input, then the next input's, in the order the inputs are given
+    #[default]
+    Concat,
+    /// The earliest transaction at the head of any input first
+    ///
+    /// Transactions without a timestamp can't be placed in time, so they are taken as soon as
+    /// they reach the head of their input.
+    Timestamp,
+    /// The lowest transaction id at the head of any input first
+    Tx,
+}
+
+impl MergeStrategy {
+    /// The order of a transaction at the head of an input, ties go to the first input
+    fn key(self, txn: &Transaction) -> (Option<Timestamp>, Option<Tx>) {
+        match self {
+            MergeStrategy::Concat => (None, None),
+            MergeStrategy::Timestamp => (txn.timestamp, None),
+            MergeStrategy::Tx => (None, Some(txn.tx)),
+        }
+    }
+}
+
+impl FromStr for MergeStrategy {
+    type Err = anyhow::Error;
+
+    /// Parse from `concat`, `timestamp` or `tx`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "concat" => Ok(MergeStrategy::Concat),
+            "timestamp" => Ok(MergeStrategy::Timestamp),
+            "tx" => Ok(MergeStrategy::Tx),
+            _ => bail!("unknown merge strategy {s}, expected concat, timestamp or tx"),
+        }
+    }
+}
+
+/// The input each merged transaction comes from
+///
+/// It's shared with the merged stream, and follows it: it always names the input of the latest
+/// transaction taken from the stream. It can be sent to another thread along with a `Reporter`.
+#[derive(Debug, Clone)]
+pub struct Provenance {
+    sources: Arc<[String]>,
+    current: Arc<AtomicUsize>,
+}
+
+impl Provenance {
+    /// The input of the latest transaction taken from the merged stream
+    #[must_use]
+    pub fn current(&self) -> &str {
+        &self.sources[self.current.load(Ordering::Relaxed)]
+    }
+}
+
+/// Expand the glob patterns among input paths, e.g. `inputs/*.csv`
+///
+/// Paths matched by a pattern are sorted, other paths are kept as is, in order.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if a pattern is invalid, or matches no file.
+pub fn expand_input_paths(patterns: &[String]) -> Result<Vec<PathBuf>> {
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        if !pattern.contains(['*', '?', '[']) {
+            paths.push(PathBuf::from(pattern));
+            continue;
+        }
+        let mut matched = glob::glob(pattern)
+            .with_context(|| format!("invalid input pattern {pattern}"))?
+            .collect::<Result<Vec<_>, _>>()?;
+        if matched.is_empty() {
+            bail!("no input matches {pattern}");
+        }
+        matched.sort();
+        paths.extend(matched);
+    }
+    Ok(paths)
+}
+
+/// Read the transactions of several CSV inputs in a `dialect`, merged as per `strategy`
+///
+/// Each input is expected to be ordered as per `strategy` already, inputs are merged as they are
+/// read rather than sorted as a whole. Every input is opened right away.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if any input can't be opened.
+pub fn merge_inputs(
+    paths: &[PathBuf],
+    strategy: MergeStrategy,
+    dialect: &Dialect,
+    verbose: bool,
+) -> Result<(impl Iterator<Item = Transaction> + use<>, Provenance)> {
+    let inputs = paths
+        .iter()
+        .map(|path| {
+            let rdr = create_dialect_reader(Some(path.clone()), dialect)
+                .with_context(|| format!("failed to open {}", path.display()))?;
+            let txns: Box<dyn Iterator<Item = Transaction>> = Box::new(
+                deserialize_dialect_transactions(Some(rdr), dialect, verbose)?,
+            );
+            Ok(txns.peekable())
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let provenance = Provenance {
+        sources: paths
+            .iter()
+            .map(|path| path.display().to_string())
+            .collect(),
+        current: Arc::new(AtomicUsize::new(0)),
+    };
+    let merge = Merge {
+        inputs,
+        strategy,
+        current: Arc::clone(&provenance.current),
+    };
+    Ok((merge, provenance))
+}
+
+struct Merge {
+    inputs: Vec<Peekable<Box<dyn Iterator<Item = Transaction>>>>,
+    strategy: MergeStrategy,
+    current: Arc<AtomicUsize>,
+}
+
+impl Iterator for Merge {
+    type Item = Transaction;
+
+    fn next(&mut self) -> Option<Transaction> {
+        let mut next = None;
+        for (i, input) in self.inputs.iter_mut().enumerate() {
+            let Some(txn) = input.peek() else {
+                continue;
+            };
+            let key = self.strategy.key(txn);
+            if next.is_none_or(|(_, min)| key < min) {
+                next = Some((i, key));
+            }
+        }
+        let (i, _) = next?;
+        self.current.store(i, Ordering::Relaxed);
+        self.inputs[i].next()
+    }
+}
@@ -1,5 +1,15 @@
+pub mod compression;
+pub mod dialect;
+pub mod diff;
 pub mod input;
+pub mod line;
+pub mod merge;
 pub mod output;
+pub mod repl;
+pub mod server;
+#[cfg(feature = "async")]
+pub mod stream;
+pub mod wal;
 
 #[cfg(test)]
 pub mod test;
@@ -1,18 +1,37 @@
 use std::{
     io::{BufWriter, Write, stdout},
-    path::PathBuf,
+    path::{Path, PathBuf},
 };
 
 use anyhow::{Context, Result};
 use csv::Writer;
 use serde::Serialize;
 
+use super::{
+    compression::{Compression, Output, compress},
+    merge::Provenance,
+};
 use crate::model::{
     account::Account,
-    common::{Amount, Client},
+    alert::{AlertRule, Severity},
+    balance::Balance,
+    common::{Amount, Client, SignedAmount, Tx},
     ledger::Ledger,
+    period::PeriodReport,
+    transaction::Transaction,
 };
 
+/// Optional columns of the ledger output, all disabled by default.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct OutputColumns {
+    /// The number of `disputes`, `resolves` and `chargebacks` applied to each account
+    pub dispute_counts: bool,
+    /// The total `fees` charged to each account
+    pub fees: bool,
+    /// The `credit_limit` of each account, and its `utilisation`, i.e. the funds overdrawn
+    pub credit: bool,
+}
+
 /// Serialize a ledger to a target writable, or stdout
 ///
 /// # Errors
@@ -20,48 +39,333 @@ use crate::model::{
 /// Returns `anyhow::Error` if it fails to flush the buffer.
 pub fn serialize_ledger<'a>(
     ledger: Ledger,
-    wtr: Option<csv::Writer<Box<dyn Write + 'a>>>,
+    wtr: Option<csv::Writer<Box<dyn Output + 'a>>>,
+    columns: OutputColumns,
+    verbose: bool,
+) -> Result<()> {
+    let mut csv_writer: csv::Writer<Box<dyn Output + 'a>> = match wtr {
+        Some(w) => w,
+        None => writer(Box::new(stdout())),
+    };
+
+    for acc in ledger.accounts.into_values() {
+        serialize_account(&mut csv_writer, acc, columns, verbose);
+    }
+
+    finish(csv_writer)
+}
+
+/// Serialize the accounts of a ledger to a target writable, by client, without consuming it
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if an account can't be serialized, or it fails to flush the buffer.
+pub fn serialize_accounts<W: Write>(ledger: &Ledger, wtr: W, columns: OutputColumns) -> Result<()> {
+    let mut accounts: Vec<_> = ledger.accounts.values().collect();
+    accounts.sort_unstable_by_key(|account| account.client.0);
+
+    let mut csv_writer = writer(wtr);
+    for account in accounts {
+        csv_writer.serialize(OutputAccountRecord::from((account, columns)))?;
+    }
+    csv_writer
+        .flush()
+        .with_context(|| "failed to flush the buffer")
+}
+
+/// Serialize a period report of a ledger to a target writable, or stdout
+///
+/// Each account is output with its opening balance at the start of the `report` period next to
+/// its current balance.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if it fails to flush the buffer.
+pub fn serialize_period_report<'a>(
+    ledger: Ledger,
+    report: &PeriodReport,
+    wtr: Option<csv::Writer<Box<dyn Output + 'a>>>,
     verbose: bool,
 ) -> Result<()> {
-    let mut csv_writer: csv::Writer<Box<dyn Write + 'a>> = match wtr {
+    let mut csv_writer: csv::Writer<Box<dyn Output + 'a>> = match wtr {
         Some(w) => w,
         None => writer(Box::new(stdout())),
     };
 
     for acc in ledger.accounts.into_values() {
-        if let Err(e) = csv_writer.serialize(OutputAccountRecord::from(acc)) {
-            if verbose {
+        let opening = report.opening(&acc);
+        if let Err(e) = csv_writer.serialize(OutputPeriodRecord::from((opening, acc)))
+            && verbose
+        {
+            eprintln!("serialization_error: {e}");
+        }
+    }
+
+    finish(csv_writer)
+}
+
+/// Process client-sorted transactions and stream the ledger to a target writable, or stdout
+///
+/// Transactions are applied on top of `ledger`. The input is declared to be sorted by client,
+/// i.e. all transactions for a client are contiguous. Each account is serialized and dropped from
+/// the ledger as soon as its client's block ends, so only a single account is kept in memory at a
+/// time. The ids of the clients already finalized are still kept, to reject their transactions.
+///
+/// Transactions for a client whose block has already ended are rejected.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if it fails to flush the buffer.
+pub fn stream_ledger<'a>(
+    mut ledger: Ledger,
+    txns: impl Iterator<Item = Transaction>,
+    wtr: Option<csv::Writer<Box<dyn Output + 'a>>>,
+    columns: OutputColumns,
+    reporter: &mut Reporter<'_>,
+) -> Result<()> {
+    let mut csv_writer: csv::Writer<Box<dyn Output + 'a>> = match wtr {
+        Some(w) => w,
+        None => writer(Box::new(stdout())),
+    };
+    let mut current: Option<Client> = None;
+
+    for txn in txns {
+        if let Some(client) = current.filter(|c| *c != txn.client)
+            && let Some(acc) = ledger.finalize(client)
+        {
+            serialize_account(&mut csv_writer, acc, columns, reporter.verbose);
+        }
+        current = Some(txn.client);
+
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+    }
+
+    for acc in ledger.accounts.into_values() {
+        serialize_account(&mut csv_writer, acc, columns, reporter.verbose);
+    }
+
+    finish(csv_writer)
+}
+
+/// Reports the outcome of the transactions applied to a ledger
+///
+/// Rejected transactions are printed to stderr if `verbose`, and written along with the reason
+/// they were rejected to the `rejects` CSV, if any. The transactions applied by the ledger on its
+/// own, and the flagged ones, are printed to stderr if `verbose`. The alerts raised by the ledger,
+/// and the flagged transactions, are written as JSON lines to `alerts`, if any.
+///
+/// Rejected transactions are reported along with the input they come from, if `provenance` is
+/// set.
+pub struct Reporter<'a> {
+    /// Print errors, warnings and info to stderr
+    pub verbose: bool,
+    /// Where rejected transactions are written to, if anywhere
+    pub rejects: Option<csv::Writer<Box<dyn Output + 'a>>>,
+    /// Where alerts are written to, if anywhere
+    pub alerts: Option<Box<dyn Output + 'a>>,
+    /// The input the transactions reported come from, if there are several
+    pub provenance: Option<Provenance>,
+}
+
+impl Reporter<'_> {
+    /// Create a reporter that only prints to stderr, if `verbose`
+    #[must_use]
+    pub fn new(verbose: bool) -> Self {
+        Self {
+            verbose,
+            rejects: None,
+            alerts: None,
+            provenance: None,
+        }
+    }
+
+    /// Report the `result` of applying a transaction to a ledger
+    ///
+    /// Also reports whatever the ledger recorded while applying it, see `report_ledger`.
+    pub fn report(&mut self, ledger: &mut Ledger, txn: &Transaction, result: Result<()>) {
+        if let Err(e) = result {
+            let source = self.provenance.as_ref().map(|p| p.current().to_owned());
+            if self.verbose {
+                match &source {
+                    Some(source) => eprintln!("warning: {source}: {e}"),
+                    None => eprintln!("warning: {e}"),
+                }
+            }
+            if let Some(rejects) = &mut self.rejects
+                && let Err(e) = rejects.serialize(OutputRejectRecord {
+                    client: txn.client,
+                    tx: txn.tx,
+                    reason: e.to_string(),
+                    source,
+                })
+                && self.verbose
+            {
                 eprintln!("serialization_error: {e}");
             }
         }
+        self.report_ledger(ledger);
+    }
+
+    /// Report what a ledger recorded on its own
+    ///
+    /// Drains the synthetic transactions recorded on the `ledger`, e.g. the resolves and
+    /// chargebacks of expired disputes, the transactions flagged by its validators, and the alerts
+    /// it raised.
+    pub fn report_ledger(&mut self, ledger: &mut Ledger) {
+        for txn in ledger.synthetic.drain(..) {
+            if self.verbose {
+                eprintln!(
+                    "info: dispute on {:?} for client {} expired, applied a {:?}",
+                    txn.tx, txn.client.0, txn.t_type
+                );
+            }
+        }
+        for flag in ledger.flags.drain(..) {
+            if self.verbose {
+                eprintln!(
+                    "info: {:?} for client {} flagged: {}",
+                    flag.tx, flag.client.0, flag.reason
+                );
+            }
+            self.write_alert(&OutputAlertRecord {
+                client: flag.client,
+                tx: flag.tx,
+                rule: AlertRule::Flagged,
+                severity: Severity::Info,
+                reason: Some(flag.reason),
+            });
+        }
+        for alert in ledger.alerts.drain(..) {
+            if self.verbose {
+                eprintln!(
+                    "info: {:?} for client {} raised a {:?} alert",
+                    alert.tx, alert.client.0, alert.rule
+                );
+            }
+            self.write_alert(&OutputAlertRecord {
+                client: alert.client,
+                tx: alert.tx,
+                rule: alert.rule,
+                severity: alert.severity,
+                reason: None,
+            });
+        }
+    }
+
+    /// Write an alert as a JSON line, if there's anywhere to write it to
+    fn write_alert(&mut self, record: &OutputAlertRecord) {
+        if let Some(alerts) = &mut self.alerts
+            && let Err(e) = serde_json::to_writer(&mut *alerts, record)
+                .map_err(anyhow::Error::from)
+                .and_then(|()| Ok(writeln!(alerts)?))
+            && self.verbose
+        {
+            eprintln!("serialization_error: {e}");
+        }
+    }
+
+    /// Flush the reports written so far, keeping their outputs open
+    ///
+    /// Lets long-running servers make each report visible as soon as it's written. Compressed
+    /// outputs can be read up to there, but are only complete once finished, see `flush`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if it fails to flush the buffer.
+    pub fn flush_buffers(&mut self) -> Result<()> {
+        if let Some(rejects) = &mut self.rejects {
+            rejects
+                .flush()
+                .with_context(|| "failed to flush the buffer")?;
+        }
+        if let Some(alerts) = &mut self.alerts {
+            alerts
+                .flush()
+                .with_context(|| "failed to flush the buffer")?;
+        }
+        Ok(())
+    }
+
+    /// Flush the reports written so far, and finish their outputs
+    ///
+    /// Compressed outputs are only complete once finished, so the outputs are dropped from the
+    /// reporter, and anything reported afterwards is only printed to stderr.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if it fails to flush the buffer, or to finish an output.
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(rejects) = self.rejects.take() {
+            finish(rejects)?;
+        }
+        if let Some(mut alerts) = self.alerts.take() {
+            alerts
+                .finish()
+                .with_context(|| "failed to flush the buffer")?;
+        }
+        Ok(())
     }
+}
 
+/// Flush a CSV writer, and finish its output
+fn finish<W: Output>(csv_writer: Writer<W>) -> Result<()> {
     csv_writer
-        .flush()
+        .into_inner()
+        .map_err(csv::IntoInnerError::into_error)
+        .and_then(|mut wtr| wtr.finish())
         .with_context(|| "failed to flush the buffer")
 }
 
+fn serialize_account<W: Write>(
+    csv_writer: &mut Writer<W>,
+    acc: Account,
+    columns: OutputColumns,
+    verbose: bool,
+) {
+    if let Err(e) = csv_writer.serialize(OutputAccountRecord::from((acc, columns)))
+        && verbose
+    {
+        eprintln!("serialization_error: {e}");
+    }
+}
+
 /// Create a file CSV writer
 ///
-/// Will write to file or stdout
+/// Will write to file or stdout. Files ending in `.gz` or `.zst` are compressed with gzip or zstd,
+/// so the output must be finished once fully written, see `Output::finish`.
 ///
 /// # Errors
 ///
 /// Returns `anyhow::Error` if the file can not be opened.
 pub fn create_csv_writer(
     output_path: Option<PathBuf>,
-) -> anyhow::Result<csv::Writer<Box<dyn Write>>> {
-    let buf_writer: Box<dyn Write> = match output_path {
-        Some(path) => {
-            let file = std::fs::File::create(path)?;
-            Box::new(BufWriter::new(file))
-        }
+) -> anyhow::Result<csv::Writer<Box<dyn Output>>> {
+    let buf_writer: Box<dyn Output> = match output_path {
+        Some(path) => create_file_writer(&path)?,
         None => Box::new(BufWriter::new(stdout())),
     };
 
     Ok(writer(buf_writer))
 }
 
+/// Create a buffered file writer
+///
+/// Files ending in `.gz` or `.zst` are compressed with gzip or zstd, so the writer must be finished
+/// once fully written, see `Output::finish`.
+///
+/// # Errors
+///
+/// Returns `anyhow::Error` if the file can not be created.
+pub fn create_file_writer(path: &Path) -> anyhow::Result<Box<dyn Output>> {
+    let file = std::fs::File::create(path)
+        .with_context(|| format!("failed to create {}", path.display()))?;
+    Ok(compress(
+        BufWriter::new(file),
+        Compression::from_extension(path),
+    )?)
+}
+
 pub fn writer<W: Write>(w: W) -> Writer<W> {
     csv::WriterBuilder::new()
         .has_headers(true)
@@ -70,21 +374,92 @@ pub fn writer<W: Write>(w: W) -> Writer<W> {
 }
 
 #[derive(Debug, Serialize)]
-struct OutputAccountRecord {
+pub(crate) struct OutputAccountRecord {
     client: Client,
-    available: Amount,
+    available: SignedAmount,
     held: Amount,
-    total: Amount,
+    total: SignedAmount,
     locked: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    disputes: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    resolves: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    chargebacks: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fees: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    credit_limit: Option<Amount>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    utilisation: Option<Amount>,
+}
+
+impl From<(Account, OutputColumns)> for OutputAccountRecord {
+    fn from((acc, columns): (Account, OutputColumns)) -> Self {
+        OutputAccountRecord::from((&acc, columns))
+    }
 }
 
-impl From<Account> for OutputAccountRecord {
-    fn from(acc: Account) -> Self {
+impl From<(&Account, OutputColumns)> for OutputAccountRecord {
+    fn from((acc, columns): (&Account, OutputColumns)) -> Self {
+        let counts = Some(acc.dispute_counts).filter(|_| columns.dispute_counts);
         OutputAccountRecord {
             client: acc.client,
-            available: acc.balance.available(),
+            available: acc.balance.signed_available(),
+            held: acc.balance.held(),
+            total: acc.balance.signed_total(),
+            locked: acc.locked,
+            disputes: counts.map(|c| c.disputes),
+            resolves: counts.map(|c| c.resolves),
+            chargebacks: counts.map(|c| c.chargebacks),
+            fees: Some(acc.fees).filter(|_| columns.fees),
+            credit_limit: Some(acc.credit_limit).filter(|_| columns.credit),
+            utilisation: Some(acc.balance.overdrawn()).filter(|_| columns.credit),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct OutputRejectRecord {
+    client: Client,
+    tx: Tx,
+    reason: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputAlertRecord {
+    client: Client,
+    tx: Tx,
+    rule: AlertRule,
+    severity: Severity,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputPeriodRecord {
+    client: Client,
+    opening_available: SignedAmount,
+    opening_held: Amount,
+    opening_total: SignedAmount,
+    available: SignedAmount,
+    held: Amount,
+    total: SignedAmount,
+    locked: bool,
+}
+
+impl From<(Balance, Account)> for OutputPeriodRecord {
+    fn from((opening, acc): (Balance, Account)) -> Self {
+        OutputPeriodRecord {
+            client: acc.client,
+            opening_available: opening.signed_available(),
+            opening_held: opening.held(),
+            opening_total: opening.signed_total(),
+            available: acc.balance.signed_available(),
             held: acc.balance.held(),
-            total: acc.balance.total(),
+            total: acc.balance.signed_total(),
             locked: acc.locked,
         }
     }
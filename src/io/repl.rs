@@ -0,0 +1,283 @@
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    io::{self, BufRead, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use csv::StringRecord;
+
+use super::{
+    input::{create_csv_reader, deserialize_transactions},
+    line::{DEFAULT_HEADERS, Line, parse_line},
+    output::{OutputColumns, serialize_accounts},
+};
+use crate::model::{
+    common::Client,
+    ledger::Ledger,
+    transaction::{Transaction, Type},
+};
+
+const HELP: &str = "\
+load <path>       load the transactions of a CSV, to be stepped through
+step [n]          apply the next n loaded transactions, 1 by default
+run               apply every loaded transaction left
+apply <row>       apply an ad-hoc transaction, as a type,client,tx,amount,timestamp,to row
+undo [n]          undo the last n steps, 1 by default
+account <client>  inspect an account, along with its deposits
+ledger            print the ledger in the output CSV format
+status            count the steps applied and the transactions left
+quit              exit the repl";
+
+/// An interactive session over a ledger, to step through transactions and undo them
+///
+/// Every transaction applied is a step, whether it's loaded from a CSV or ad-hoc, and whether
+/// it's rejected or not. Undoing steps replays the rest on a brand new ledger, so the ledger and
+/// its validators end up in the same state as if the undone steps never happened. Undone loaded
+/// transactions can be stepped through again, undone ad-hoc ones are dropped.
+pub struct Repl<F> {
+    pub ledger: Ledger,
+    pub columns: OutputColumns,
+    pub verbose: bool,
+    /// Creates the brand new ledgers undone steps are replayed on
+    new_ledger: F,
+    /// The loaded transactions left to step through
+    pending: VecDeque<Transaction>,
+    /// The steps applied so far, along with whether they were loaded
+    steps: Vec<(Transaction, bool)>,
+}
+
+impl<F: FnMut() -> Result<Ledger>> Repl<F> {
+    /// Start a session on a ledger created by `new_ledger`
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the ledger can't be created.
+    pub fn new(mut new_ledger: F, columns: OutputColumns, verbose: bool) -> Result<Self> {
+        Ok(Self {
+            ledger: new_ledger()?,
+            columns,
+            verbose,
+            new_ledger,
+            pending: VecDeque::new(),
+            steps: Vec::new(),
+        })
+    }
+
+    /// Load transactions to be stepped through, after any left
+    pub fn load(&mut self, txns: impl Iterator<Item = Transaction>) -> usize {
+        let before = self.pending.len();
+        self.pending.extend(txns);
+        self.pending.len() - before
+    }
+
+    /// Read commands from `input` and write their output, until it ends or the session is quit
+    ///
+    /// # Errors
+    ///
+    /// Returns `io::Error` if a command can't be read, or its output written.
+    pub fn run<R: BufRead, W: Write>(&mut self, input: R, mut output: W) -> io::Result<()> {
+        write!(output, "> ")?;
+        output.flush()?;
+        for line in input.lines() {
+            let Some(reply) = self.execute(&line?) else {
+                break;
+            };
+            if !reply.is_empty() {
+                writeln!(output, "{reply}")?;
+            }
+            write!(output, "> ")?;
+            output.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Execute a command, and get its output
+    ///
+    /// Returns `None` once the session is quit.
+    pub fn execute(&mut self, line: &str) -> Option<String> {
+        let line = line.trim();
+        let (command, args) = line.split_once(' ').unwrap_or((line, ""));
+        let args = args.trim();
+        let reply = match command {
+            "" => String::new(),
+            "quit" | "exit" => return None,
+            "help" => HELP.to_owned(),
+            "load" => match create_csv_reader(Some(args.into()))
+                .and_then(|rdr| deserialize_transactions(Some(rdr), self.verbose))
+            {
+                Ok(txns) => format!(
+                    "loaded {} transactions from {}",
+                    self.load(txns),
+                    Path::new(args).display()
+                ),
+                Err(e) => format!("error: {e}"),
+            },
+            "step" => match count(args) {
+                Ok(n) => self.step(n),
+                Err(e) => e,
+            },
+            "run" => {
+                let steps = self.pending.len();
+                let mut rejected = 0;
+                while let Some(txn) = self.pending.pop_front() {
+                    if self.apply(txn, true).is_err() {
+                        rejected += 1;
+                    }
+                }
+                format!("applied {steps} transactions, {rejected} rejected")
+            }
+            "apply" => match parse_line(args, &StringRecord::from(DEFAULT_HEADERS.to_vec())) {
+                Ok(Line::Transaction(txn)) => self.apply(txn, false).unwrap_or_else(|e| e),
+                Ok(Line::Header(_)) => "error: expected a transaction, not a header".to_owned(),
+                Err(e) => format!("error: {e}"),
+            },
+            "undo" => match count(args) {
+                Ok(n) => self.undo(n),
+                Err(e) => e,
+            },
+            "account" => match args.parse().map(Client) {
+                Ok(client) => self.account(client),
+                Err(e) => format!("error: invalid client {args}: {e}"),
+            },
+            "ledger" => {
+                let mut dump = Vec::new();
+                match serialize_accounts(&self.ledger, &mut dump, self.columns) {
+                    Ok(()) => String::from_utf8_lossy(&dump).trim_end().to_owned(),
+                    Err(e) => format!("error: {e}"),
+                }
+            }
+            "status" => format!(
+                "{} steps applied, {} transactions left",
+                self.steps.len(),
+                self.pending.len()
+            ),
+            _ => format!("unknown command {command}, try help"),
+        };
+        Some(reply)
+    }
+
+    /// Apply the next `n` loaded transactions, as many as there are left
+    fn step(&mut self, n: usize) -> String {
+        let mut replies = Vec::new();
+        for _ in 0..n {
+            let Some(txn) = self.pending.pop_front() else {
+                replies.push("no transactions left".to_owned());
+                break;
+            };
+            replies.push(self.apply(txn, true).unwrap_or_else(|e| e));
+        }
+        replies.join("\n")
+    }
+
+    /// Apply a transaction as a step, and describe the outcome
+    ///
+    /// Returns the description as an error if the transaction is rejected.
+    fn apply(&mut self, txn: Transaction, loaded: bool) -> Result<String, String> {
+        let result = self.ledger.apply(txn);
+        self.steps.push((txn, loaded));
+
+        let mut reply = describe(&txn);
+        match &result {
+            Ok(()) => reply.push_str(": ok"),
+            Err(e) => {
+                let _ = write!(reply, ": error: {e}");
+            }
+        }
+        for synthetic in self.ledger.synthetic.drain(..) {
+            let _ = write!(
+                reply,
+                "\n  expired dispute closed by {}",
+                describe(&synthetic)
+            );
+        }
+        for flag in self.ledger.flags.drain(..) {
+            let _ = write!(reply, "\n  flagged: {}", flag.reason);
+        }
+        for alert in self.ledger.alerts.drain(..) {
+            let _ = write!(reply, "\n  alert: {:?} ({:?})", alert.rule, alert.severity);
+        }
+        match result {
+            Ok(()) => Ok(reply),
+            Err(_) => Err(reply),
+        }
+    }
+
+    /// Undo the last `n` steps, as many as there are
+    fn undo(&mut self, n: usize) -> String {
+        let mut ledger = match (self.new_ledger)() {
+            Ok(ledger) => ledger,
+            Err(e) => return format!("error: {e}"),
+        };
+        let n = n.min(self.steps.len());
+        let undone = self.steps.split_off(self.steps.len() - n);
+        for (txn, loaded) in undone.into_iter().rev() {
+            if loaded {
+                self.pending.push_front(txn);
+            }
+        }
+
+        for (txn, _) in &self.steps {
+            let _ = ledger.apply(*txn);
+        }
+        ledger.synthetic.clear();
+        ledger.flags.clear();
+        ledger.alerts.clear();
+        self.ledger = ledger;
+        format!("undid {n} steps, {} transactions left", self.pending.len())
+    }
+
+    /// Describe an account, along with its deposits by transaction
+    fn account(&self, client: Client) -> String {
+        let Some(account) = self.ledger.accounts.get(&client) else {
+            return format!("no account for client {}", client.0);
+        };
+        let balance = account.balance;
+        let mut reply = format!(
+            "client {}: available {}, held {}, total {}, locked {}",
+            client.0,
+            balance.signed_available(),
+            balance.held(),
+            balance.signed_total(),
+            account.locked
+        );
+        let mut deposits: Vec<_> = account.deposits.iter().collect();
+        deposits.sort_unstable_by_key(|(tx, _)| **tx);
+        for (tx, deposit) in deposits {
+            let _ = write!(
+                reply,
+                "\n  deposit {:?}: {}, {:?}, disputed {}, charged back {}",
+                tx, deposit.amount, deposit.dispute_status, deposit.disputed, deposit.charged_back
+            );
+        }
+        reply
+    }
+}
+
+/// Parse an optional count of steps, 1 by default
+fn count(args: &str) -> Result<usize, String> {
+    if args.is_empty() {
+        return Ok(1);
+    }
+    args.parse()
+        .map_err(|e| format!("error: invalid count {args}: {e}"))
+}
+
+/// Describe a transaction in a few words
+fn describe(txn: &Transaction) -> String {
+    let what = match txn.t_type {
+        Type::Deposit(deposit) => format!("deposit of {}", deposit.amount),
+        Type::Withdrawal(withdrawal) => format!("withdrawal of {}", withdrawal.amount),
+        Type::Transfer(transfer) => {
+            format!(
+                "transfer of {} to client {}",
+                transfer.amount, transfer.to.0
+            )
+        }
+        Type::Dispute(_) => "dispute".to_owned(),
+        Type::Resolve(_) => "resolve".to_owned(),
+        Type::Chargeback(_) => "chargeback".to_owned(),
+    };
+    format!("{:?} {what} for client {}", txn.tx, txn.client.0)
+}
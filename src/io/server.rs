@@ -0,0 +1,152 @@
+use anyhow::{Context, Result};
+use serde::Serialize;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+use super::{
+    input::InputTransactionRecord,
+    output::{OutputAccountRecord, OutputColumns, Reporter},
+};
+use crate::model::{common::Client, ledger::Ledger, transaction::Transaction};
+
+/// A local HTTP/JSON API over a ledger kept in memory
+///
+/// - `POST /transactions` applies a transaction, with the same fields as the input CSV, and
+///   responds with the resulting account.
+/// - `GET /accounts/{client}` responds with an account.
+/// - `GET /accounts` responds with every account, by client.
+///
+/// Accounts have the same shape as the output CSV. Transactions go through the same validation as
+/// in batch mode: malformed ones are answered with a 400, and rejected ones with a 422, along with
+/// the error. Either way, they are reported to the `reporter`.
+pub struct LedgerServer<'a> {
+    pub ledger: Ledger,
+    pub columns: OutputColumns,
+    pub reporter: Reporter<'a>,
+}
+
+/// A response, before it's sent
+#[derive(Debug, PartialEq, Eq)]
+pub struct JsonResponse {
+    pub status: u16,
+    pub body: String,
+}
+
+#[derive(Debug, Serialize)]
+struct OutputError {
+    error: String,
+}
+
+impl LedgerServer<'_> {
+    /// Serve the requests received by `server`, until it's unblocked
+    ///
+    /// The reports are flushed after each request, and their outputs finished once unblocked.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the reports can't be flushed once done.
+    pub fn serve(&mut self, server: &Server) -> Result<()> {
+        for request in server.incoming_requests() {
+            if let Err(e) = self.respond(request)
+                && self.reporter.verbose
+            {
+                eprintln!("warning: failed to respond: {e}");
+            }
+        }
+        self.reporter.flush()
+    }
+
+    /// Handle a request
+    ///
+    /// Responds to any `method` and `url` that is not part of the API with a 404.
+    pub fn handle(&mut self, method: &Method, url: &str, body: &str) -> JsonResponse {
+        let path = url.split('?').next().unwrap_or_default();
+        match (method, path.trim_end_matches('/')) {
+            (Method::Post, "/transactions") => self.post_transaction(body),
+            (Method::Get, "/accounts") => {
+                let mut accounts: Vec<_> = self.ledger.accounts.values().collect();
+                accounts.sort_unstable_by_key(|account| account.client.0);
+                let records: Vec<_> = accounts
+                    .into_iter()
+                    .map(|account| OutputAccountRecord::from((account, self.columns)))
+                    .collect();
+                json(200, &records)
+            }
+            (Method::Get, path) if path.starts_with("/accounts/") => {
+                let Ok(client) = path["/accounts/".len()..].parse().map(Client) else {
+                    return error(400, format!("invalid client in {path}"));
+                };
+                match self.ledger.accounts.get(&client) {
+                    Some(account) => json(200, &OutputAccountRecord::from((account, self.columns))),
+                    None => error(404, format!("account {} not found", client.0)),
+                }
+            }
+            _ => error(404, format!("{method} {path} not found")),
+        }
+    }
+
+    fn post_transaction(&mut self, body: &str) -> JsonResponse {
+        let txn = match serde_json::from_str::<InputTransactionRecord>(body) {
+            Ok(record) => Transaction::try_from(record).map_err(|e| e.to_string()),
+            Err(e) => Err(e.to_string()),
+        };
+        let txn = match txn {
+            Ok(txn) => txn,
+            Err(e) => {
+                if self.reporter.verbose {
+                    eprintln!("input_mapping_error: {e}");
+                }
+                return error(400, e);
+            }
+        };
+
+        let result = self.ledger.apply(txn);
+        let rejected = result.as_ref().err().map(ToString::to_string);
+        self.reporter.report(&mut self.ledger, &txn, result);
+        if let Some(e) = rejected {
+            return error(422, e);
+        }
+        match self.ledger.accounts.get(&txn.client) {
+            Some(account) => json(200, &OutputAccountRecord::from((account, self.columns))),
+            None => error(404, format!("account {} not found", txn.client.0)),
+        }
+    }
+
+    fn respond(&mut self, mut request: Request) -> Result<()> {
+        let mut body = String::new();
+        request
+            .as_reader()
+            .read_to_string(&mut body)
+            .with_context(|| "failed to read the request body")?;
+
+        let response = self.handle(request.method(), request.url(), &body);
+        // Before responding, so the reports are up to date once the client gets the response
+        if let Err(e) = self.reporter.flush_buffers()
+            && self.reporter.verbose
+        {
+            eprintln!("warning: failed to flush the reports: {e}");
+        }
+        let content_type = Header::from_bytes("Content-Type", "application/json")
+            .map_err(|()| anyhow::anyhow!("invalid header"))?;
+        request
+            .respond(
+                Response::from_string(response.body)
+                    .with_status_code(response.status)
+                    .with_header(content_type),
+            )
+            .with_context(|| "failed to send the response")
+    }
+}
+
+fn json<T: Serialize>(status: u16, value: &T) -> JsonResponse {
+    match serde_json::to_string(value) {
+        Ok(body) => JsonResponse { status, body },
+        Err(e) => error(500, e.to_string()),
+    }
+}
+
+fn error(status: u16, error: String) -> JsonResponse {
+    JsonResponse {
+        status,
+        body: serde_json::to_string(&OutputError { error }).unwrap_or_default(),
+    }
+}
@@ -0,0 +1,90 @@
+use std::pin::pin;
+
+use csv::StringRecord;
+use futures_util::{Stream, StreamExt, stream};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+
+use super::{
+    line::{Line, parse_line, parse_record},
+    output::Reporter,
+};
+use crate::model::{ledger::Ledger, transaction::Transaction};
+
+/// Read the transactions of a CSV as they arrive, e.g. from a socket or a pipe
+///
+/// The first line is the header, as in batch mode. Rows that can't be mapped to a transaction are
+/// skipped, and reported if `verbose`, as well as any other header sent later on. The stream ends
+/// once `rdr` does, or can't be read anymore.
+///
+/// Rows are read line by line, so quoted fields spanning several lines are not supported.
+pub fn transaction_stream<R: AsyncRead + Unpin>(
+    rdr: R,
+    verbose: bool,
+) -> impl Stream<Item = Transaction> {
+    let lines = BufReader::new(rdr).lines();
+    stream::unfold(
+        (lines, None::<StringRecord>),
+        move |(mut lines, mut headers)| async move {
+            loop {
+                let line = match lines.next_line().await {
+                    Ok(Some(line)) => line,
+                    Ok(None) => return None,
+                    Err(e) => {
+                        if verbose {
+                            eprintln!("warning: failed to read the input: {e}");
+                        }
+                        return None;
+                    }
+                };
+                let line = line.trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let Some(current) = &headers else {
+                    match parse_record(line) {
+                        Ok(record) => headers = Some(record),
+                        Err(e) => {
+                            if verbose {
+                                eprintln!("input_mapping_error: invalid header: {e}");
+                            }
+                        }
+                    }
+                    continue;
+                };
+                match parse_line(line, current) {
+                    Ok(Line::Transaction(txn)) => return Some((txn, (lines, headers))),
+                    Ok(Line::Header(_)) => {
+                        if verbose {
+                            eprintln!("warning: ignoring a header past the first line");
+                        }
+                    }
+                    Err(e) => {
+                        if verbose {
+                            eprintln!("input_mapping_error: {e}");
+                        }
+                    }
+                }
+            }
+        },
+    )
+}
+
+/// Process transactions as they arrive
+///
+/// The async counterpart of `process_transactions`: applies all `txns` on top of `ledger`, and
+/// returns the fully processed ledger once the stream ends. Only the stream is awaited, the
+/// ledger itself is synchronous. The returned future is `Send` as long as the stream is, so it can
+/// be spawned on a multi-threaded runtime.
+pub async fn process_transaction_stream(
+    mut ledger: Ledger,
+    txns: impl Stream<Item = Transaction>,
+    reporter: &mut Reporter<'_>,
+) -> Ledger {
+    let mut txns = pin!(txns);
+    while let Some(txn) = txns.next().await {
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+    }
+
+    ledger
+}
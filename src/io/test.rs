@@ -1,10 +1,39 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{Arc, mpsc},
+    thread,
+};
+
+use csv::StringRecord;
 use rust_decimal_macros::dec;
+use tiny_http::{Method, Server};
 
 use crate::{
-    io::input::{InputTransactionRecord, reader},
+    io::{
+        compression::{Compression, Output, compress, decompress},
+        dialect::Dialect,
+        diff::{diff_ledgers, read_ledger, write_differences},
+        input::{
+            InputTransactionRecord, deserialize_dialect_transactions, deserialize_transactions,
+            reader,
+        },
+        line::{Command, Line, LineServer, Request, accept, parse_line},
+        merge::{MergeStrategy, expand_input_paths, merge_inputs},
+        output::{
+            OutputColumns, Reporter, create_csv_writer, serialize_ledger, stream_ledger, writer,
+        },
+        repl::Repl,
+        server::{JsonResponse, LedgerServer},
+        wal::Wal,
+    },
     model::{
         common::{Amount, Client, Tx},
-        transaction::{Deposit, DisputeStatus, Transaction, Type},
+        fee::{Fee, FeeSchedule},
+        ledger::Ledger,
+        risk::{RiskConfig, Velocity},
+        transaction::{Deposit, Transaction, Transfer, Type, Withdrawal},
+        validator::{NewAccountOutflows, RoundDeposits},
     },
 };
 
@@ -31,12 +60,1192 @@ fn negative_deserialization() {
     assert_eq!(
         txns[0],
         Transaction {
-            t_type: Type::Deposit(Deposit {
-                amount: Amount::try_from(dec!(1.2345)).expect("non-negative constant"),
-                dispute_status: DisputeStatus::default(),
+            t_type: Type::Deposit(Deposit::new(
+                Amount::try_from(dec!(1.2345)).expect("non-negative constant")
+            )),
+            client: Client(1),
+            tx: Tx(1),
+            timestamp: None,
+        }
+    );
+}
+
+#[test]
+fn stream_client_sorted_ledger() {
+    let input_data = "type,client,tx,amount
+                    deposit,1,1,1.5
+                    withdrawal,1,2,0.5
+                    deposit,2,3,2
+                    deposit,1,4,10"; // <- Client 1 reappears after its block ended
+
+    let rdr = reader(Box::new(input_data.as_bytes()) as Box<dyn std::io::Read>);
+    let txns = deserialize_transactions(Some(rdr), false).expect("should deserialize");
+
+    let mut output = Vec::new();
+    stream_ledger(
+        Ledger::default(),
+        txns,
+        Some(writer(Box::new(&mut output) as Box<dyn Output>)),
+        OutputColumns::default(),
+        &mut Reporter::new(false),
+    )
+    .expect("buffer should flush");
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "client,available,held,total,locked\n1,1.0,0,1.0,false\n2,2,0,2,false\n"
+    );
+}
+
+#[test]
+fn deserialize_optional_timestamps() {
+    let input_data = "type,client,tx,amount,timestamp
+                    deposit,1,1,1.5,2026-09-30T23:59:59Z
+                    deposit,1,2,1.5,2026-09-30T23:59:59+02:00
+                    deposit,1,3,1.5,
+                    deposit,1,4,1.5,yesterday"; // <- Invalid timestamp
+
+    let mut rdr = reader(input_data.as_bytes());
+    let txns: Vec<Transaction> = rdr
+        .deserialize::<InputTransactionRecord>()
+        .flatten()
+        .flat_map(Transaction::try_from)
+        .collect();
+
+    assert_eq!(txns.len(), 3);
+    assert_eq!(
+        txns[0].timestamp.map(|t| t.to_string()),
+        Some("2026-09-30T23:59:59+00:00".to_string())
+    );
+    assert_eq!(
+        txns[1].timestamp.map(|t| t.to_string()),
+        Some("2026-09-30T21:59:59+00:00".to_string())
+    );
+    assert_eq!(txns[2].timestamp, None);
+}
+
+#[test]
+fn deserialize_optional_dispute_amounts() {
+    let input_data = "type,client,tx,amount
+                    dispute,1,1,
+                    resolve,1,1,0.5
+                    chargeback,1,1,-0.5"; // <- Only checked if partial disputes are enabled
+
+    let mut rdr = reader(input_data.as_bytes());
+    let txns: Vec<Transaction> = rdr
+        .deserialize::<InputTransactionRecord>()
+        .flatten()
+        .flat_map(Transaction::try_from)
+        .collect();
+
+    assert_eq!(txns.len(), 3);
+    assert_eq!(txns[0].t_type, Type::Dispute(None));
+    assert_eq!(txns[1].t_type, Type::Resolve(Some(dec!(0.5))));
+    assert_eq!(txns[2].t_type, Type::Chargeback(Some(dec!(-0.5))));
+}
+
+#[test]
+fn deserialize_transfers() {
+    let input_data = "type,client,tx,amount,to
+                    transfer,1,1,2.5,2
+                    transfer,1,2,2.5,
+                    transfer,1,3,,2
+                    deposit,1,4,1.0"; // <- The recipient column is optional for other types
+
+    let mut rdr = reader(input_data.as_bytes());
+    let txns: Vec<Transaction> = rdr
+        .deserialize::<InputTransactionRecord>()
+        .flatten()
+        .flat_map(Transaction::try_from)
+        .collect();
+
+    assert_eq!(txns.len(), 2);
+    assert_eq!(
+        txns[0].t_type,
+        Type::Transfer(Transfer {
+            to: Client(2),
+            amount: Amount::try_from(dec!(2.5)).expect("non-negative constant"),
+        })
+    );
+    assert_eq!(txns[1].tx, Tx(4));
+}
+
+#[test]
+fn deserialize_apply_serialize_wide_ids() {
+    let input_data = "type,client,tx,amount
+                    deposit,70000,5000000000,2.0
+                    deposit,18446744073709551615,18446744073709551615,1.0
+                    dispute,70000,5000000000,
+                    deposit,1,18446744073709551616,1.0"; // <- Tx overflows u64
+
+    let txns: Vec<Transaction> =
+        deserialize_transactions(Some(reader(Box::new(input_data.as_bytes()))), false)
+            .expect("should deserialize")
+            .collect();
+    assert_eq!(txns.len(), 3);
+    assert_eq!(txns[0].client, Client(70_000));
+    assert_eq!(txns[0].tx, Tx(5_000_000_000));
+    assert_eq!(txns[1].client, Client(u64::MAX));
+
+    let mut ledger = Ledger::default();
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+    let mut output = Vec::new();
+    serialize_ledger(
+        ledger,
+        Some(writer(Box::new(&mut output) as Box<dyn Output>)),
+        OutputColumns::default(),
+        false,
+    )
+    .expect("buffer should flush");
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    let mut rows: Vec<&str> = output.lines().skip(1).collect();
+    rows.sort_unstable();
+    assert_eq!(
+        rows,
+        [
+            "18446744073709551615,1.0,0,1.0,false",
+            "70000,0.0,2.0,2.0,false"
+        ]
+    );
+}
+
+#[test]
+fn serialize_dispute_counts() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        Type::Deposit(Deposit::new(
+            Amount::try_from(dec!(2)).expect("non-negative constant"),
+        )),
+        Type::Dispute(None),
+        Type::Resolve(None),
+    ];
+    for t_type in txns {
+        ledger
+            .apply(Transaction {
+                t_type,
+                client: Client(1),
+                tx: Tx(1),
+                timestamp: None,
+            })
+            .expect("txns are valid");
+    }
+
+    let mut output = Vec::new();
+    serialize_ledger(
+        ledger,
+        Some(writer(Box::new(&mut output) as Box<dyn Output>)),
+        OutputColumns {
+            dispute_counts: true,
+            fees: false,
+            credit: false,
+        },
+        false,
+    )
+    .expect("buffer should flush");
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "client,available,held,total,locked,disputes,resolves,chargebacks\n1,2,0,2,false,1,1,0\n"
+    );
+}
+
+#[test]
+fn deserialize_fee_schedule() {
+    let schedule = "house = 0
+
+                    [withdrawal]
+                    flat = \"0.5\"
+                    percent = 1
+                    max = \"10\"";
+
+    let schedule: FeeSchedule = toml::from_str(schedule).expect("valid fee schedule");
+    assert_eq!(schedule.house, Client(0));
+    assert_eq!(
+        schedule.withdrawal,
+        Some(Fee {
+            flat: Amount::try_from(dec!(0.5)).expect("non-negative constant"),
+            percent: Amount::try_from(dec!(1)).expect("non-negative constant"),
+            min: None,
+            max: Some(Amount::try_from(dec!(10)).expect("non-negative constant")),
+        })
+    );
+    assert_eq!(schedule.deposit, None);
+
+    toml::from_str::<FeeSchedule>("house = 0\n[withdrawal]\nflat = \"-1\"")
+        .expect_err("negative fees are not allowed");
+    toml::from_str::<FeeSchedule>("house = 0\n[withdrawl]\nflat = \"1\"")
+        .expect_err("unknown transaction types are not allowed");
+}
+
+#[test]
+fn serialize_overdrawn_account() {
+    let mut ledger = Ledger::default();
+    ledger.credit_limits.insert(
+        Client(1),
+        Amount::try_from(dec!(5)).expect("non-negative constant"),
+    );
+    ledger
+        .apply(Transaction {
+            t_type: Type::Withdrawal(Withdrawal {
+                amount: Amount::try_from(dec!(2)).expect("non-negative constant"),
             }),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
+        })
+        .expect("the withdrawal is within the limit");
+
+    let mut output = Vec::new();
+    serialize_ledger(
+        ledger,
+        Some(writer(Box::new(&mut output) as Box<dyn Output>)),
+        OutputColumns {
+            credit: true,
+            ..OutputColumns::default()
+        },
+        false,
+    )
+    .expect("buffer should flush");
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "client,available,held,total,locked,credit_limit,utilisation\n1,-2,0,-2,false,5,2\n"
+    );
+}
+
+#[test]
+fn deserialize_risk_limits() {
+    let config = "[default]
+                  max_withdrawals = { count = 5, window = \"1h\" }
+                  lock_on_breach = true
+
+                  [clients.7]
+                  max_withdrawal = \"10000\"";
+
+    let config: RiskConfig = toml::from_str(config).expect("valid risk limits");
+    let default = config.limits_for(Client(1)).expect("default limits");
+    assert_eq!(
+        default.max_withdrawals,
+        Some(Velocity {
+            count: 5,
+            window: chrono::TimeDelta::hours(1),
+        })
+    );
+    assert!(default.lock_on_breach);
+
+    let own = config.limits_for(Client(7)).expect("own limits");
+    assert_eq!(
+        own.max_withdrawal,
+        Some(Amount::try_from(dec!(10000)).expect("non-negative constant"))
+    );
+    assert_eq!(own.max_withdrawals, None);
+    assert!(!own.lock_on_breach);
+}
+
+#[test]
+fn report_rejected_transactions() {
+    let mut ledger = Ledger::default();
+    let txn = Transaction {
+        t_type: Type::Withdrawal(Withdrawal {
+            amount: Amount::try_from(dec!(2)).expect("non-negative constant"),
+        }),
+        client: Client(1),
+        tx: Tx(1),
+        timestamp: None,
+    };
+
+    let mut output = Vec::new();
+    let mut reporter = Reporter::new(false);
+    reporter.rejects = Some(writer(Box::new(&mut output) as Box<dyn Output>));
+    let result = ledger.apply(txn);
+    reporter.report(&mut ledger, &txn, result);
+    let result = ledger.apply(Transaction {
+        t_type: Type::Deposit(Deposit::new(Amount::ZERO)),
+        tx: Tx(2),
+        ..txn
+    });
+    reporter.report(&mut ledger, &txn, result);
+    reporter.flush().expect("buffer should flush");
+    drop(reporter);
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "client,tx,reason\n1,1,insufficient funds for tx Tx(1)\n"
+    );
+}
+
+#[test]
+fn report_alerts_as_json_lines() {
+    let mut ledger = Ledger::default();
+    ledger.validators.push(Box::new(RoundDeposits {
+        multiple: Amount::try_from(dec!(1)).expect("non-negative constant"),
+    }));
+    let deposit = Transaction {
+        t_type: Type::Deposit(Deposit::new(
+            Amount::try_from(dec!(2)).expect("non-negative constant"),
+        )),
+        client: Client(1),
+        tx: Tx(1),
+        timestamp: None,
+    };
+    let chargeback = Transaction {
+        t_type: Type::Chargeback(None),
+        ..deposit
+    };
+
+    let mut output = Vec::new();
+    let mut reporter = Reporter::new(false);
+    reporter.alerts = Some(Box::new(&mut output));
+    for txn in [
+        deposit,
+        Transaction {
+            t_type: Type::Dispute(None),
+            ..deposit
+        },
+        chargeback,
+    ] {
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+    }
+    reporter.flush().expect("buffer should flush");
+    drop(reporter);
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "{\"client\":1,\"tx\":1,\"rule\":\"flagged\",\"severity\":\"info\",\
+        \"reason\":\"round deposit, a multiple of 1\"}\n\
+        {\"client\":1,\"tx\":1,\"rule\":\"account_locked\",\"severity\":\"critical\"}\n"
+    );
+}
+
+#[test]
+fn diff_ledger_outputs() {
+    let old = read_ledger(
+        "client,available,held,total,locked\n\
+        1,1.0000,0,1.0000,false\n\
+        2,2,0,2,false\n\
+        3,1,0,1,false\n"
+            .as_bytes(),
+    )
+    .expect("valid ledger");
+    let new = read_ledger(
+        "client,available,held,total,locked,fees\n\
+        1,1.00005,0,1.00005,true,0\n\
+        2,1,1,2,false,0\n\
+        4,0,0,0,false,0\n"
+            .as_bytes(),
+    )
+    .expect("valid ledger, extra columns are ignored");
+
+    let tolerance = Amount::try_from(dec!(0.0001)).expect("non-negative constant");
+    let mut output = Vec::new();
+    write_differences(&mut output, &diff_ledgers(&old, &new, tolerance))
+        .expect("buffer should flush");
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        "client,change,field,old,new\n\
+        1,changed,locked,false,true\n\
+        2,changed,available,2,1\n\
+        2,changed,held,0,1\n\
+        3,removed,,,\n\
+        4,added,,,\n"
+    );
+    assert!(diff_ledgers(&old, &old, Amount::ZERO).is_empty());
+}
+
+#[test]
+fn diff_ledger_outputs_too_far_apart_to_subtract() {
+    let old = read_ledger(
+        "client,available,held,total,locked\n\
+        1,79228162514264337593543950335,0,79228162514264337593543950335,false\n"
+            .as_bytes(),
+    )
+    .expect("valid ledger");
+    let new = read_ledger(
+        "client,available,held,total,locked\n\
+        1,-79228162514264337593543950335,0,79228162514264337593543950335,false\n"
+            .as_bytes(),
+    )
+    .expect("valid ledger");
+
+    let differences = diff_ledgers(&old, &new, Amount::ZERO);
+    assert_eq!(differences.len(), 1);
+    assert_eq!(differences[0].field, Some("available"));
+}
+
+#[test]
+fn negative_diff_rejects_malformed_ledger() {
+    read_ledger("client,available,held,total,locked\n1,one,0,1,false\n".as_bytes())
+        .expect_err("available is not an amount");
+}
+
+#[test]
+fn serve_rejected_and_malformed_transactions() {
+    let mut server = LedgerServer {
+        ledger: Ledger::default(),
+        columns: OutputColumns::default(),
+        reporter: Reporter::new(false),
+    };
+
+    assert_eq!(
+        server.handle(
+            &Method::Post,
+            "/transactions",
+            r#"{"type":"withdrawal","client":1,"tx":1,"amount":"2"}"#
+        ),
+        JsonResponse {
+            status: 422,
+            body: r#"{"error":"insufficient funds for tx Tx(1)"}"#.to_owned(),
+        }
+    );
+    assert_eq!(
+        server
+            .handle(
+                &Method::Post,
+                "/transactions",
+                r#"{"type":"deposit","client":1,"tx":1}"#
+            )
+            .status,
+        400
+    );
+    assert_eq!(server.handle(&Method::Get, "/accounts/one", "").status, 400);
+    assert_eq!(server.handle(&Method::Delete, "/accounts", "").status, 404);
+}
+
+#[test]
+fn serve_ledger_on_localhost() {
+    let server = Arc::new(Server::http("127.0.0.1:0").expect("a free local port"));
+    let addr = server.server_addr().to_ip().expect("an ip address");
+    let serving = {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            LedgerServer {
+                ledger: Ledger::default(),
+                columns: OutputColumns::default(),
+                reporter: Reporter::new(false),
+            }
+            .serve(&server)
+        })
+    };
+
+    let posted = http(
+        addr,
+        "POST /transactions",
+        r#"{"type":"deposit","client":1,"tx":1,"amount":"2.5"}"#,
+    );
+    let account = http(addr, "GET /accounts/1", "");
+    let accounts = http(addr, "GET /accounts", "");
+    let missing = http(addr, "GET /accounts/2", "");
+    server.unblock();
+    serving
+        .join()
+        .expect("server should not panic")
+        .expect("reports should flush");
+
+    let record = r#"{"client":1,"available":"2.5","held":"0","total":"2.5","locked":false}"#;
+    assert!(posted.starts_with("HTTP/1.1 200"));
+    assert!(posted.ends_with(record));
+    assert!(account.ends_with(record));
+    assert!(accounts.ends_with(&format!("[{record}]")));
+    assert!(missing.starts_with("HTTP/1.1 404"));
+}
+
+#[test]
+fn serve_flushes_rejects_after_each_request() {
+    let path =
+        std::env::temp_dir().join(format!("little_ledger_{}_rejects.csv", std::process::id()));
+    let mut reporter = Reporter::new(false);
+    reporter.rejects = Some(create_csv_writer(Some(path.clone())).expect("writable temp file"));
+    let server = Arc::new(Server::http("127.0.0.1:0").expect("a free local port"));
+    let addr = server.server_addr().to_ip().expect("an ip address");
+    let serving = {
+        let server = Arc::clone(&server);
+        thread::spawn(move || {
+            LedgerServer {
+                ledger: Ledger::default(),
+                columns: OutputColumns::default(),
+                reporter,
+            }
+            .serve(&server)
+        })
+    };
+
+    let rejected = http(
+        addr,
+        "POST /transactions",
+        r#"{"type":"withdrawal","client":1,"tx":1,"amount":"2"}"#,
+    );
+    let rejects = std::fs::read_to_string(&path).expect("readable temp file");
+    server.unblock();
+    serving
+        .join()
+        .expect("server should not panic")
+        .expect("reports should flush");
+
+    assert!(rejected.starts_with("HTTP/1.1 422"));
+    assert_eq!(
+        rejects,
+        "client,tx,reason\n1,1,insufficient funds for tx Tx(1)\n"
+    );
+    std::fs::remove_file(path).expect("removable temp file");
+}
+
+/// Send a request over a fresh connection, and read the whole response
+fn http(addr: SocketAddr, request: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).expect("server should be listening");
+    write!(
+        stream,
+        "{request} HTTP/1.1\r\nHost: {addr}\r\nConnection: close\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .expect("request should be sent");
+    let mut response = String::new();
+    stream
+        .read_to_string(&mut response)
+        .expect("response should be received");
+    response
+}
+
+#[test]
+fn parse_protocol_lines() {
+    let headers = StringRecord::from(vec!["type", "client", "tx", "amount"]);
+    assert_eq!(
+        parse_line("dispute, 1, 7", &headers).expect("trailing columns can be left out"),
+        Line::Transaction(Transaction {
+            t_type: Type::Dispute(None),
+            client: Client(1),
+            tx: Tx(7),
+            timestamp: None,
+        })
+    );
+    assert_eq!(
+        parse_line("type,tx,client", &headers).expect("valid header"),
+        Line::Header(StringRecord::from(vec!["type", "tx", "client"]))
+    );
+    parse_line("withdrawal,1,2", &headers).expect_err("withdrawals need an amount");
+}
+
+#[test]
+fn acknowledge_protocol_commands() {
+    let mut server = LineServer {
+        ledger: Ledger::default(),
+        columns: OutputColumns::default(),
+        reporter: Reporter::new(false),
+    };
+    let deposit = Transaction {
+        t_type: Type::Deposit(Deposit::new(
+            Amount::try_from(dec!(1)).expect("non-negative constant"),
+        )),
+        client: Client(1),
+        tx: Tx(1),
+        timestamp: None,
+    };
+
+    assert_eq!(server.handle(Command::Apply(deposit)), "ok");
+    assert_eq!(
+        server.handle(Command::Apply(Transaction {
+            t_type: Type::Withdrawal(Withdrawal {
+                amount: Amount::try_from(dec!(2)).expect("non-negative constant"),
+            }),
+            tx: Tx(2),
+            ..deposit
+        })),
+        "error insufficient_funds: insufficient funds for tx Tx(2)"
+    );
+    server.handle(Command::Apply(Transaction {
+        t_type: Type::Dispute(None),
+        ..deposit
+    }));
+    server.handle(Command::Apply(Transaction {
+        t_type: Type::Chargeback(None),
+        ..deposit
+    }));
+    assert_eq!(
+        server.handle(Command::Apply(Transaction {
+            tx: Tx(3),
+            ..deposit
+        })),
+        "error locked: account is locked 1"
+    );
+    assert_eq!(
+        server.handle(Command::Dump),
+        "client,available,held,total,locked\n1,0,0,0,true\nok"
+    );
+}
+
+#[test]
+fn stream_lines_from_concurrent_connections() {
+    let listener = TcpListener::bind("127.0.0.1:0").expect("a free local port");
+    let addr = listener.local_addr().expect("a bound address");
+    let (requests, received) = mpsc::channel();
+    accept(
+        move || listener.accept().map(|(stream, _)| stream),
+        requests,
+    );
+    thread::spawn(move || {
+        LineServer {
+            ledger: Ledger::default(),
+            columns: OutputColumns::default(),
+            reporter: Reporter::new(false),
+        }
+        .serve(&received)
+    });
+
+    let first = TcpStream::connect(addr).expect("server should be listening");
+    let second = TcpStream::connect(addr).expect("server should be listening");
+    assert_eq!(send(&first, "deposit,1,1,2"), "ok");
+    assert_eq!(send(&second, "type,tx,client,amount"), "ok");
+    assert_eq!(send(&second, "deposit,2,2,1"), "ok");
+    assert!(send(&second, "deposit,2").starts_with("error malformed:"));
+    assert_eq!(send(&first, "dump"), "client,available,held,total,locked");
+    let mut dump = Vec::new();
+    let mut lines = BufReader::new(&first).lines();
+    for line in lines.by_ref() {
+        let line = line.expect("dump should be received");
+        if line == "ok" {
+            break;
+        }
+        dump.push(line);
+    }
+    assert_eq!(dump, ["1,2,0,2,false", "2,1,0,1,false"]);
+}
+
+#[test]
+fn stream_flushes_rejects_after_each_request_until_stopped() {
+    let path = std::env::temp_dir().join(format!(
+        "little_ledger_{}_line_rejects.csv",
+        std::process::id()
+    ));
+    let mut reporter = Reporter::new(false);
+    reporter.rejects = Some(create_csv_writer(Some(path.clone())).expect("writable temp file"));
+    let (requests, received) = mpsc::channel();
+    let serving = thread::spawn(move || {
+        LineServer {
+            ledger: Ledger::default(),
+            columns: OutputColumns::default(),
+            reporter,
+        }
+        .serve(&received)
+    });
+    let request = |command| {
+        let (reply, replied) = mpsc::channel();
+        requests
+            .send(Request { command, reply })
+            .expect("server should be running");
+        replied.recv().expect("server should reply")
+    };
+
+    let rejected = request(Command::Apply(Transaction {
+        t_type: Type::Withdrawal(Withdrawal {
+            amount: Amount::try_from(dec!(2)).expect("non-negative constant"),
+        }),
+        client: Client(1),
+        tx: Tx(1),
+        timestamp: None,
+    }));
+    let rejects = std::fs::read_to_string(&path).expect("readable temp file");
+    assert_eq!(request(Command::Stop), "ok");
+    serving
+        .join()
+        .expect("server should not panic")
+        .expect("reports should flush");
+
+    assert!(rejected.starts_with("error insufficient_funds"));
+    assert_eq!(
+        rejects,
+        "client,tx,reason\n1,1,insufficient funds for tx Tx(1)\n"
+    );
+    std::fs::remove_file(path).expect("removable temp file");
+}
+
+/// Send a line over a connection, and read the first line of the reply
+fn send(stream: &TcpStream, line: &str) -> String {
+    writeln!(&mut &*stream, "{line}").expect("line should be sent");
+    let mut reply = String::new();
+    let mut byte = [0];
+    while (&mut &*stream).read_exact(&mut byte).is_ok() && byte[0] != b'\n' {
+        reply.push(char::from(byte[0]));
+    }
+    reply
+}
+
+#[test]
+fn repl_steps_through_and_undoes_transactions() {
+    let mut repl = Repl::new(|| Ok(Ledger::default()), OutputColumns::default(), false)
+        .expect("ledger should be created");
+    let txns = deserialize_transactions(
+        Some(reader(Box::new(
+            "type,client,tx,amount\n\
+            deposit,1,1,2\n\
+            dispute,1,1,\n\
+            chargeback,1,1,\n"
+                .as_bytes(),
+        ))),
+        false,
+    )
+    .expect("valid reader");
+    assert_eq!(repl.load(txns), 3);
+
+    let mut execute = |line| repl.execute(line).expect("not quit");
+    assert_eq!(
+        execute("step 2"),
+        "Tx(1) deposit of 2 for client 1: ok\nTx(1) dispute for client 1: ok"
+    );
+    assert_eq!(
+        execute("account 1"),
+        "client 1: available 0, held 2, total 2, locked false\n  \
+        deposit Tx(1): 2, Open, disputed 2, charged back 0"
+    );
+    assert_eq!(
+        execute("apply deposit,1,2,1"),
+        "Tx(2) deposit of 1 for client 1: ok"
+    );
+    assert_eq!(execute("undo 2"), "undid 2 steps, 2 transactions left");
+    assert_eq!(
+        execute("account 1"),
+        "client 1: available 2, held 0, total 2, locked false\n  \
+        deposit Tx(1): 2, None, disputed 0, charged back 0"
+    );
+    assert_eq!(execute("run"), "applied 2 transactions, 0 rejected");
+    assert_eq!(
+        execute("ledger"),
+        "client,available,held,total,locked\n1,0,0,0,true"
+    );
+    assert_eq!(execute("step"), "no transactions left");
+    assert!(repl.execute("quit").is_none());
+}
+
+#[test]
+fn resume_from_write_ahead_log() {
+    let input = "type,client,tx,amount\n\
+                deposit,1,1,2\n\
+                deposit,1,2,3\n\
+                dispute,1,1,\n\
+                withdrawal,1,3,1\n";
+    let txns = || {
+        deserialize_transactions(Some(reader(Box::new(input.as_bytes()))), false)
+            .expect("valid reader")
+    };
+    let path = std::env::temp_dir().join(format!("little_ledger_{}.wal", std::process::id()));
+
+    // The run dies while logging the fourth transaction, past a checkpoint after the second
+    let mut ledger = Ledger::default();
+    let mut wal = Wal::create(&path, 2).expect("writable log");
+    for (offset, txn) in (0..).zip(txns().take(3)) {
+        wal.log(offset, &txn).expect("writable log");
+        let _ = ledger.apply(txn);
+        wal.commit(offset + 1, &ledger)
+            .expect("writable checkpoint");
+    }
+    drop(wal);
+    std::fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .and_then(|mut log| log.write_all(b"{\"offset\":3,\"tx"))
+        .expect("writable log");
+
+    let mut resumed = Ledger::default();
+    let (_, recovery) = Wal::resume(&path, 2, &mut resumed).expect("recoverable log");
+    assert_eq!(recovery.offset, 3);
+    assert_eq!(recovery.last.map(|txn| txn.tx), Some(Tx(1)));
+    let account = resumed.accounts.get(&Client(1)).expect("recovered account");
+    assert_eq!(account.balance, ledger.accounts[&Client(1)].balance);
+    assert_eq!(account.deposits, ledger.accounts[&Client(1)].deposits);
+
+    let rest: Vec<_> = recovery
+        .skip(txns())
+        .expect("same input")
+        .map(|txn| txn.tx)
+        .collect();
+    assert_eq!(rest, vec![Tx(3)]);
+    assert!(recovery.skip(txns().skip(1)).is_err());
+
+    // Resuming again picks up from the checkpoint taken on resume
+    let (_, again) = Wal::resume(&path, 2, &mut Ledger::default()).expect("recoverable log");
+    assert_eq!(again, recovery);
+
+    std::fs::remove_file(&path).expect("removable log");
+    let mut checkpoint = path.into_os_string();
+    checkpoint.push(".checkpoint");
+    std::fs::remove_file(checkpoint).expect("removable checkpoint");
+}
+
+#[test]
+fn resume_validators_from_write_ahead_log() {
+    let input = "type,client,tx,amount,timestamp\n\
+                deposit,1,1,5,2026-01-01T00:00:00Z\n\
+                withdrawal,1,2,1,2026-02-01T00:00:00Z\n";
+    let txns = || {
+        deserialize_transactions(Some(reader(Box::new(input.as_bytes()))), false)
+            .expect("valid reader")
+    };
+    let guarded = || {
+        let mut ledger = Ledger::default();
+        ledger
+            .validators
+            .push(Box::new(NewAccountOutflows::new(chrono::TimeDelta::days(
+                7,
+            ))));
+        ledger
+    };
+    let path = std::env::temp_dir().join(format!(
+        "little_ledger_{}_validators.wal",
+        std::process::id()
+    ));
+
+    // The run dies right after checkpointing the deposit
+    let mut ledger = guarded();
+    let mut wal = Wal::create(&path, 1).expect("writable log");
+    let deposit = txns().next().expect("a deposit");
+    wal.log(0, &deposit).expect("writable log");
+    ledger.apply(deposit).expect("valid deposit");
+    wal.commit(1, &ledger).expect("writable checkpoint");
+    drop(wal);
+
+    // The account was opened a month before the withdrawal, as in an uninterrupted run
+    let mut resumed = guarded();
+    let (_, recovery) = Wal::resume(&path, 1, &mut resumed).expect("recoverable log");
+    for txn in recovery.skip(txns()).expect("same input") {
+        resumed.apply(txn).expect("the account is old enough");
+    }
+    assert_eq!(
+        resumed.accounts[&Client(1)].balance.available(),
+        Amount::try_from(dec!(4)).expect("non-negative constant")
+    );
+
+    // Validators must match the checkpointed ones
+    assert!(Wal::resume(&path, 1, &mut Ledger::default()).is_err());
+
+    std::fs::remove_file(&path).expect("removable log");
+    let mut checkpoint = path.into_os_string();
+    checkpoint.push(".checkpoint");
+    std::fs::remove_file(checkpoint).expect("removable checkpoint");
+}
+
+#[test]
+fn merge_inputs_with_provenance() {
+    let dir = std::env::temp_dir().join(format!("little_ledger_{}_merge", std::process::id()));
+    std::fs::create_dir_all(&dir).expect("writable temp dir");
+    let card = dir.join("card.csv");
+    let bank = dir.join("bank.csv");
+    std::fs::write(
+        &card,
+        "type,client,tx,amount,timestamp\n\
+        deposit,1,2,1,2026-09-01T10:00:00Z\n\
+        withdrawal,1,4,5,2026-09-03T10:00:00Z\n",
+    )
+    .expect("writable input");
+    std::fs::write(
+        &bank,
+        "type,client,tx,amount,timestamp\n\
+        deposit,1,1,1,2026-09-02T10:00:00Z\n\
+        deposit,1,3,1,2026-09-04T10:00:00Z\n",
+    )
+    .expect("writable input");
+
+    let paths =
+        expand_input_paths(&[dir.join("*.csv").display().to_string()]).expect("matching pattern");
+    assert_eq!(paths, vec![bank.clone(), card.clone()]);
+    assert!(expand_input_paths(&[dir.join("*.tsv").display().to_string()]).is_err());
+
+    let order = |strategy| {
+        let (txns, _) = merge_inputs(
+            &[card.clone(), bank.clone()],
+            strategy,
+            &Dialect::default(),
+            false,
+        )
+        .expect("readable inputs");
+        txns.map(|txn| txn.tx.0).collect::<Vec<_>>()
+    };
+    assert_eq!(order(MergeStrategy::Concat), vec![2, 4, 1, 3]);
+    assert_eq!(order(MergeStrategy::Timestamp), vec![2, 1, 4, 3]);
+    assert_eq!(order(MergeStrategy::Tx), vec![1, 2, 3, 4]);
+
+    let (txns, provenance) = merge_inputs(
+        &[card.clone(), bank],
+        MergeStrategy::Timestamp,
+        &Dialect::default(),
+        false,
+    )
+    .expect("readable inputs");
+    let mut output = Vec::new();
+    let mut reporter = Reporter::new(false);
+    reporter.rejects = Some(writer(Box::new(&mut output) as Box<dyn Output>));
+    reporter.provenance = Some(provenance);
+    let mut ledger = Ledger::default();
+    for txn in txns {
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+    }
+    reporter.flush().expect("buffer should flush");
+    drop(reporter);
+
+    let output = String::from_utf8(output).expect("should be valid utf8");
+    assert_eq!(
+        output,
+        format!(
+            "client,tx,reason,source\n1,4,insufficient funds for tx Tx(4),{}\n",
+            card.display()
+        )
+    );
+    std::fs::remove_dir_all(dir).expect("removable temp dir");
+}
+
+#[test]
+fn compressed_round_trip() {
+    let input = "type,client,tx,amount\ndeposit,1,1,2\n";
+    for compression in [Compression::None, Compression::Gzip, Compression::Zstd] {
+        let mut compressed = Vec::new();
+        let mut wtr = compress(&mut compressed, compression).expect("valid encoder");
+        wtr.write_all(input.as_bytes()).expect("writable buffer");
+        wtr.finish().expect("writable buffer");
+        drop(wtr);
+        assert_eq!(Compression::from_magic(&compressed), compression);
+
+        let rdr = decompress(std::io::Cursor::new(compressed)).expect("valid decoder");
+        let txns: Vec<_> = deserialize_transactions(Some(reader(rdr)), false)
+            .expect("valid reader")
+            .collect();
+        assert_eq!(txns.len(), 1, "{compression:?}");
+    }
+    assert_eq!(
+        Compression::from_extension(std::path::Path::new("day.csv.zst")),
+        Compression::Zstd
+    );
+}
+
+/// An output shared with the test, so it can be read while a reporter still holds it
+#[derive(Debug, Clone, Default)]
+struct SharedOutput(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+impl Write for SharedOutput {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().expect("no panic while writing").write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Output for SharedOutput {}
+
+#[test]
+fn report_rejects_to_compressed_output() {
+    let txn = Transaction {
+        t_type: Type::Withdrawal(Withdrawal {
+            amount: Amount::try_from(dec!(2)).expect("non-negative constant"),
+        }),
+        client: Client(1),
+        tx: Tx(1),
+        timestamp: None,
+    };
+    let expected = "client,tx,reason\n1,1,insufficient funds for tx Tx(1)\n";
+
+    for compression in [Compression::Gzip, Compression::Zstd] {
+        let output = SharedOutput::default();
+        let mut ledger = Ledger::default();
+        let mut reporter = Reporter::new(false);
+        reporter.rejects = Some(writer(
+            compress(output.clone(), compression).expect("valid encoder"),
+        ));
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+        reporter.flush().expect("buffer should flush");
+
+        let compressed = output.0.lock().expect("no panic while writing").clone();
+        if compression == Compression::Gzip {
+            // The trailer ends with the size of the uncompressed stream
+            let size = compressed[compressed.len() - 4..].to_vec();
+            assert_eq!(
+                size,
+                u32::try_from(expected.len())
+                    .expect("small constant")
+                    .to_le_bytes()
+            );
         }
+        let mut rdr = decompress(std::io::Cursor::new(compressed)).expect("valid decoder");
+        let mut decompressed = String::new();
+        rdr.read_to_string(&mut decompressed)
+            .expect("the compressed stream is complete");
+        assert_eq!(decompressed, expected, "{compression:?}");
+    }
+}
+
+#[test]
+fn deserialize_partner_dialect() {
+    let dialect: Dialect = toml::from_str(
+        r#"
+        delimiter = ";"
+        decimal_separator = ","
+
+        [columns]
+        type = "kind"
+        client = "customer_id"
+        tx = "txid"
+
+        [types]
+        credit = "deposit"
+        debit = "withdrawal"
+        "#,
+    )
+    .expect("valid dialect");
+    dialect.validate().expect("usable dialect");
+
+    let input = "kind;customer_id;txid;amount\n\
+                credit;1;1;2,5\n\
+                debit;1;2;1,25\n\
+                refund;1;3;1\n";
+    let txns: Vec<_> = deserialize_dialect_transactions(
+        Some(dialect.reader(Box::new(input.as_bytes()))),
+        &dialect,
+        false,
+    )
+    .expect("valid reader")
+    .collect();
+    assert_eq!(
+        txns.iter().map(|txn| txn.t_type).collect::<Vec<_>>(),
+        vec![
+            Type::Deposit(Deposit::new(
+                Amount::try_from(dec!(2.5)).expect("valid amount")
+            )),
+            Type::Withdrawal(Withdrawal {
+                amount: Amount::try_from(dec!(1.25)).expect("valid amount")
+            }),
+        ]
+    );
+
+    // Headerless inputs take their columns from the dialect
+    let headerless = Dialect {
+        has_headers: false,
+        headers: Some(vec![
+            "client".into(),
+            "tx".into(),
+            "type".into(),
+            "amount".into(),
+        ]),
+        ..Dialect::default()
+    };
+    let txns: Vec<_> = deserialize_dialect_transactions(
+        Some(headerless.reader(Box::new("1,1,deposit,2\n".as_bytes()))),
+        &headerless,
+        false,
+    )
+    .expect("valid reader")
+    .collect();
+    assert_eq!(txns.len(), 1);
+
+    let invalid = Dialect {
+        decimal_separator: '_',
+        ..Dialect::default()
+    };
+    assert!(invalid.validate().is_err());
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn stream_transactions_from_async_reader() {
+    use futures_util::StreamExt;
+    use tokio::io::AsyncWriteExt;
+
+    use crate::io::stream::transaction_stream;
+
+    let (mut wtr, rdr) = tokio::io::duplex(64);
+    let txns = transaction_stream(rdr, false);
+    tokio::pin!(txns);
+
+    wtr.write_all(b"type, client, tx, amount\n\ndeposit,1,1,2\n")
+        .await
+        .expect("writable pipe");
+    assert_eq!(
+        txns.next().await,
+        Some(Transaction {
+            client: Client(1),
+            tx: Tx(1),
+            t_type: Type::Deposit(Deposit::new(
+                Amount::try_from(dec!(2)).expect("valid amount")
+            )),
+            timestamp: None,
+        })
+    );
+
+    // Malformed rows and later headers are skipped
+    wtr.write_all(b"withdrawal,1,2,1.2.3\ntype,client,tx\nwithdrawal,1,3,1\n")
+        .await
+        .expect("writable pipe");
+    drop(wtr);
+    let rest: Vec<_> = txns.map(|txn| txn.tx).collect().await;
+    assert_eq!(rest, vec![Tx(3)]);
+}
+
+#[cfg(feature = "async")]
+#[tokio::test(flavor = "multi_thread")]
+async fn spawn_transaction_stream_processing() {
+    use crate::{
+        io::stream::{process_transaction_stream, transaction_stream},
+        model::validator::NewAccountOutflows,
+    };
+
+    // The header is parsed as CSV too, quotes included
+    let input = "\"type\",client,tx,\"amount\"\n\
+                deposit,1,1,2\n\
+                withdrawal,1,2,0.5\n";
+    let mut ledger = Ledger::default();
+    ledger
+        .validators
+        .push(Box::new(NewAccountOutflows::new(chrono::TimeDelta::days(
+            1,
+        ))));
+    let ledger = tokio::spawn(async move {
+        let mut reporter = Reporter::new(false);
+        process_transaction_stream(
+            ledger,
+            transaction_stream(input.as_bytes(), false),
+            &mut reporter,
+        )
+        .await
+    })
+    .await
+    .expect("processing doesn't panic");
+
+    let account = ledger.accounts.get(&Client(1)).expect("account exists");
+    assert_eq!(
+        account.balance.available(),
+        Amount::try_from(dec!(1.5)).expect("valid amount")
+    );
+}
+
+#[cfg(feature = "async")]
+#[tokio::test]
+async fn process_transaction_stream_into_ledger() {
+    use crate::io::stream::{process_transaction_stream, transaction_stream};
+
+    let input = "type,client,tx,amount\n\
+                deposit,1,1,2\n\
+                withdrawal,1,2,5\n\
+                withdrawal,1,3,0.5\n";
+    let mut reporter = Reporter::new(false);
+    let ledger = process_transaction_stream(
+        Ledger::default(),
+        transaction_stream(input.as_bytes(), false),
+        &mut reporter,
+    )
+    .await;
+
+    let account = ledger.accounts.get(&Client(1)).expect("account exists");
+    assert_eq!(
+        account.balance.available(),
+        Amount::try_from(dec!(1.5)).expect("valid amount")
     );
 }
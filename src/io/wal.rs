@@ -0,0 +1,286 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, BufWriter, ErrorKind, Write},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{Context, Result, bail};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::model::{ledger::Ledger, transaction::Transaction};
+
+/// A write-ahead log of the transactions applied to a ledger, along with checkpoints of its state
+///
+/// Each transaction is logged, with its offset in the input, before it's applied, whether it ends
+/// up rejected or not, so the ledger can be rebuilt by applying the log again. Every so often the
+/// state of the ledger is checkpointed next to the log, at `<path>.checkpoint`, and the log is
+/// cleared, so recovering never replays more than `checkpoint_every` transactions.
+///
+/// Records are flushed to the OS as they are logged, so they survive the process dying, and
+/// synced to disk on every checkpoint.
+#[derive(Debug)]
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    /// How many transactions are logged between checkpoints
+    pub checkpoint_every: u64,
+    /// The transactions logged since the latest checkpoint
+    logged: u64,
+    /// The latest transaction logged, to check the input against when resuming
+    last: Option<Transaction>,
+}
+
+/// Where a resumed run continues from
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+pub struct Recovery {
+    /// The number of input transactions already applied to the ledger
+    pub offset: u64,
+    /// The latest of them, if any
+    pub last: Option<Transaction>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct WalRecord {
+    offset: u64,
+    txn: Transaction,
+}
+
+#[derive(Debug, Serialize)]
+struct CheckpointRecord<'a> {
+    offset: u64,
+    last: Option<Transaction>,
+    ledger: &'a Ledger,
+    validators: Vec<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Checkpoint {
+    offset: u64,
+    last: Option<Transaction>,
+    ledger: Ledger,
+    validators: Vec<Value>,
+}
+
+impl Wal {
+    /// Start a brand new log at `path`, dropping any previous log and checkpoint
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the log can't be created, or the previous checkpoint removed.
+    pub fn create(path: &Path, checkpoint_every: u64) -> Result<Self> {
+        match fs::remove_file(checkpoint_path(path)) {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e).with_context(|| "failed to remove the previous checkpoint");
+            }
+            _ => {}
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            file: create_log(path)?,
+            checkpoint_every,
+            logged: 0,
+            last: None,
+        })
+    }
+
+    /// Recover `ledger` from the log at `path` and its latest checkpoint, and keep logging
+    ///
+    /// The ledger is restored from the checkpoint, if any, along with the state of its validators,
+    /// then the transactions logged after it are applied again. A last record cut short, i.e. the
+    /// process died while logging it, is dropped as it was never applied. The recovered ledger is
+    /// checkpointed right away.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the log or the checkpoint can't be read, or are corrupted, or the
+    /// validators of `ledger` differ from the checkpointed ones.
+    pub fn resume(
+        path: &Path,
+        checkpoint_every: u64,
+        ledger: &mut Ledger,
+    ) -> Result<(Self, Recovery)> {
+        let mut recovery = Recovery::default();
+        match File::open(checkpoint_path(path)) {
+            Ok(file) => {
+                let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(file))
+                    .with_context(|| "invalid checkpoint")?;
+                ledger.restore(checkpoint.ledger);
+                ledger.restore_validators(checkpoint.validators)?;
+                recovery = Recovery {
+                    offset: checkpoint.offset,
+                    last: checkpoint.last,
+                };
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| "failed to open the checkpoint"),
+        }
+
+        let records = match File::open(path) {
+            Ok(file) => read_records(file)?,
+            Err(e) if e.kind() == ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| "failed to open the write-ahead log"),
+        };
+        // Records older than the checkpoint are left over if the process died right after it
+        let checkpointed = recovery.offset;
+        for record in records.into_iter().skip_while(|r| r.offset < checkpointed) {
+            if record.offset != recovery.offset {
+                bail!(
+                    "write-ahead log skips from offset {} to {}",
+                    recovery.offset,
+                    record.offset
+                );
+            }
+            let _ = ledger.apply(record.txn);
+            recovery = Recovery {
+                offset: record.offset + 1,
+                last: Some(record.txn),
+            };
+        }
+        ledger.synthetic.clear();
+        ledger.flags.clear();
+        ledger.alerts.clear();
+
+        let mut wal = Self {
+            path: path.to_path_buf(),
+            file: OpenOptions::new()
+                .append(true)
+                .create(true)
+                .open(path)
+                .with_context(|| "failed to open the write-ahead log")?,
+            checkpoint_every,
+            logged: 0,
+            last: recovery.last,
+        };
+        wal.checkpoint(recovery.offset, ledger)?;
+        Ok((wal, recovery))
+    }
+
+    /// Log a transaction, at its `offset` in the input, before it's applied
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the record can't be written.
+    pub fn log(&mut self, offset: u64, txn: &Transaction) -> Result<()> {
+        let mut line = serde_json::to_vec(&WalRecord { offset, txn: *txn })?;
+        line.push(b'\n');
+        self.file
+            .write_all(&line)
+            .with_context(|| "failed to write to the write-ahead log")?;
+        self.logged += 1;
+        self.last = Some(*txn);
+        Ok(())
+    }
+
+    /// Checkpoint `ledger` if enough transactions were logged since the latest checkpoint
+    ///
+    /// `offset` is the number of input transactions applied to the ledger so far.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the checkpoint is due but can't be written.
+    pub fn commit(&mut self, offset: u64, ledger: &Ledger) -> Result<()> {
+        if self.logged >= self.checkpoint_every {
+            self.checkpoint(offset, ledger)?;
+        }
+        Ok(())
+    }
+
+    /// Checkpoint `ledger`, and clear the log
+    ///
+    /// `offset` is the number of input transactions applied to the ledger so far. The checkpoint
+    /// is written aside, then moved over the previous one, so a checkpoint is never left half
+    /// written.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the checkpoint can't be written, or the log cleared.
+    pub fn checkpoint(&mut self, offset: u64, ledger: &Ledger) -> Result<()> {
+        let path = checkpoint_path(&self.path);
+        let mut aside = path.clone().into_os_string();
+        aside.push(".tmp");
+
+        let mut wtr = BufWriter::new(
+            File::create(&aside).with_context(|| "failed to create the checkpoint")?,
+        );
+        let record = CheckpointRecord {
+            offset,
+            last: self.last,
+            ledger,
+            validators: ledger.validator_states(),
+        };
+        serde_json::to_writer(&mut wtr, &record)
+            .with_context(|| "failed to write the checkpoint")?;
+        wtr.into_inner()
+            .map_err(io::IntoInnerError::into_error)
+            .and_then(|file| file.sync_all())
+            .with_context(|| "failed to sync the checkpoint")?;
+        fs::rename(&aside, &path).with_context(|| "failed to move the checkpoint")?;
+
+        self.file = create_log(&self.path)?;
+        self.file
+            .sync_all()
+            .with_context(|| "failed to sync the write-ahead log")?;
+        self.logged = 0;
+        Ok(())
+    }
+}
+
+impl Recovery {
+    /// Skip the input transactions already applied to the ledger
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the input doesn't match the one the ledger was recovered from,
+    /// i.e. it's shorter or its latest applied transaction differs.
+    pub fn skip<I: Iterator<Item = Transaction>>(&self, mut txns: I) -> Result<I> {
+        let mut last = None;
+        for skipped in 0..self.offset {
+            let Some(txn) = txns.next() else {
+                bail!(
+                    "input ended after {skipped} transactions, expected at least {}",
+                    self.offset
+                );
+            };
+            last = Some(txn);
+        }
+        if last != self.last {
+            bail!(
+                "input changed since it was logged, transaction {} is {:?} instead of {:?}",
+                self.offset,
+                last,
+                self.last
+            );
+        }
+        Ok(txns)
+    }
+}
+
+fn checkpoint_path(path: &Path) -> PathBuf {
+    let mut checkpoint = path.to_path_buf().into_os_string();
+    checkpoint.push(".checkpoint");
+    PathBuf::from(checkpoint)
+}
+
+fn create_log(path: &Path) -> Result<File> {
+    File::create(path).with_context(|| format!("failed to create {}", path.display()))
+}
+
+/// Read the records of a log, dropping the last one if it was cut short
+fn read_records(file: File) -> Result<Vec<WalRecord>> {
+    let mut lines = BufReader::new(file).lines().peekable();
+    let mut records = Vec::new();
+    while let Some(line) = lines.next() {
+        let line = line.with_context(|| "failed to read the write-ahead log")?;
+        match serde_json::from_str(&line) {
+            Ok(record) => records.push(record),
+            Err(_) if lines.peek().is_none() => break,
+            Err(e) => {
+                return Err(e).with_context(|| {
+                    format!("invalid write-ahead log record {}", records.len() + 1)
+                });
+            }
+        }
+    }
+    Ok(records)
+}
@@ -1,30 +1,403 @@
-use std::{error::Error, path::PathBuf};
+use std::{
+    error::Error,
+    fs::File,
+    io::{BufReader, stdin, stdout},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+    sync::{Arc, mpsc},
+};
+
+#[cfg(unix)]
+use std::os::unix::net::UnixListener;
 
-use clap::{Parser, command};
+use anyhow::{Context, anyhow, bail};
+use chrono::TimeDelta;
+use clap::{Args, CommandFactory, Parser, Subcommand, error::ErrorKind};
 use little_ledger::{
     io::{
-        input::{create_csv_reader, deserialize_transactions},
-        output::serialize_ledger,
+        compression::decompress,
+        diff::{diff_ledgers, read_ledger, write_differences},
+        input::{
+            create_csv_reader, create_dialect_reader, deserialize_dialect_transactions,
+            deserialize_transactions, read_credit_limits, read_dialect, read_fee_schedule,
+            read_risk_limits,
+        },
+        line::{self, LineServer, Request, accept},
+        merge::{MergeStrategy, Provenance, expand_input_paths, merge_inputs},
+        output::{
+            OutputColumns, Reporter, create_csv_writer, create_file_writer, serialize_ledger,
+            serialize_period_report, stream_ledger,
+        },
+        repl::Repl,
+        server::LedgerServer,
+        wal::{Recovery, Wal},
+    },
+    model::{
+        common::{Amount, Timestamp},
+        ledger::{DisputeExpiry, DisputeWindow, ExpiryPolicy, Ledger},
+        period::PeriodReport,
+        transaction::Transaction,
+        validator::{NewAccountOutflows, RoundDeposits},
     },
-    model::{ledger::Ledger, transaction::Transaction},
 };
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use tiny_http::Server;
+
+use config::ConfigFile;
+
+mod config;
+
+/// How many withdrawals and transfers in a row can be rejected before raising an alert, by default.
+const DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS: u32 = 3;
+
+/// How many transactions are applied between checkpoints of the write-ahead log, by default.
+const DEFAULT_CHECKPOINT_EVERY: u64 = 10_000;
 
 /// Command line arguments for the Petit Payments Engine.
 #[derive(Parser, Debug)]
-#[command(author, version, about, long_about)]
+#[command(
+    author,
+    version,
+    about,
+    long_about,
+    args_conflicts_with_subcommands = true
+)]
 struct Cli {
-    /// Optional path to the input CSV file.
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// Optional paths or glob patterns of the input CSV files, e.g. 'inputs/*.csv'.
     ///
-    /// The application will read from stdin if not provided.
-    #[arg(index = 1)]
-    input_path: Option<PathBuf>,
+    /// The application will read from stdin if not provided. Several inputs are merged as per
+    /// `--merge`, and rejected transactions are reported along with the input they come from.
+    #[arg(index = 1, value_name = "INPUT_PATHS")]
+    input_paths: Vec<String>,
+
+    /// Outputs the ledger as of an instant, e.g. 2026-09-30T23:59:59Z.
+    ///
+    /// When set, only transactions timestamped up to this instant (RFC 3339) are applied.
+    /// Transactions without a timestamp are ignored whenever a time query is requested.
+    #[arg(long, value_name = "TIMESTAMP")]
+    as_of: Option<Timestamp>,
+
+    /// Outputs a period report starting at an instant, e.g. 2026-09-01T00:00:00Z.
+    ///
+    /// When set, each account is output with its opening balance at this instant (RFC 3339) next
+    /// to its closing balance at `--as-of`, or after all transactions if not provided.
+    #[arg(long, value_name = "TIMESTAMP")]
+    since: Option<Timestamp>,
+
+    /// Resumes an interrupted run from its write-ahead log, instead of starting over.
+    ///
+    /// The input and options must be the same as the interrupted run's: the transactions already
+    /// applied are skipped, and the rest are applied on the recovered ledger. Reports only cover
+    /// the transactions applied after resuming.
+    #[arg(long)]
+    resume: bool,
+
+    /// Path to a TOML configuration file, `little_ledger.toml` in the current directory by default.
+    ///
+    /// The file sets any option of the ledger, the reports and the input, in a [ledger], [report]
+    /// and [input] table. Options given on the command line override the file's, and switches the
+    /// file turns on can be turned off, e.g. --verbose=false.
+    #[arg(long, value_name = "PATH", global = true)]
+    config: Option<PathBuf>,
+
+    #[command(flatten)]
+    input: InputArgs,
+
+    #[command(flatten)]
+    ledger: LedgerArgs,
+
+    #[command(flatten)]
+    report: ReportArgs,
+}
+
+/// Subcommands other than processing transactions, the default.
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Compares two ledger outputs, e.g. from two runs over the same input.
+    ///
+    /// Reports added and removed clients, and changes in available, held, total and locked, as a
+    /// CSV to stdout. Exits with code 1 if there are any differences, or 2 if an output can't be
+    /// read.
+    Diff {
+        /// Path to the old ledger output.
+        old: PathBuf,
+
+        /// Path to the new ledger output.
+        new: PathBuf,
+
+        /// Amounts that differ by no more than this are considered equal, e.g. 0.0001.
+        #[arg(long, value_name = "AMOUNT", default_value = "0", value_parser = parse_amount)]
+        tolerance: Amount,
+    },
+
+    /// Serves an HTTP/JSON API over a ledger kept in memory.
+    ///
+    /// POST /transactions applies a transaction, GET /accounts/{client} gets an account, and GET
+    /// /accounts gets the full ledger.
+    Serve {
+        /// The local address to listen on.
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        bind: String,
+
+        #[command(flatten)]
+        ledger: LedgerArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+
+    /// Explores a ledger interactively, stepping through transactions and undoing them.
+    ///
+    /// Type help once started for the available commands.
+    Repl {
+        /// Optional path to a CSV of transactions to step through.
+        input_path: Option<PathBuf>,
+
+        /// Enables verbose output.
+        #[arg(
+        short, long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+        verbose: Option<bool>,
+
+        /// Outputs the number of disputes, resolves and chargebacks applied to each account.
+        #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+        dispute_counts: Option<bool>,
+
+        #[command(flatten)]
+        ledger: LedgerArgs,
+    },
+
+    /// Manages the configuration file.
+    Config {
+        #[command(subcommand)]
+        command: ConfigCommand,
+    },
+
+    /// Streams CSV rows over a TCP or Unix domain socket into a ledger kept in memory.
+    ///
+    /// Each line is acknowledged with `ok`, or `error <kind>: <reason>`. Any number of clients can
+    /// connect at once, and send `dump` to get the ledger in the output CSV format.
+    Listen {
+        /// The local TCP address to listen on, e.g. 127.0.0.1:9000.
+        #[arg(long, value_name = "ADDRESS", required_unless_present = "unix")]
+        tcp: Option<String>,
+
+        /// The path of the Unix domain socket to listen on.
+        #[cfg(unix)]
+        #[arg(long, value_name = "PATH", conflicts_with = "tcp")]
+        unix: Option<PathBuf>,
+
+        #[command(flatten)]
+        ledger: LedgerArgs,
 
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+}
+
+/// Subcommands of the configuration file.
+#[derive(Subcommand, Debug)]
+enum ConfigCommand {
+    /// Prints the effective configuration as TOML, once merged with the options given.
+    Show {
+        #[command(flatten)]
+        input: InputArgs,
+
+        #[command(flatten)]
+        ledger: LedgerArgs,
+
+        #[command(flatten)]
+        report: ReportArgs,
+    },
+}
+
+/// How the ledger processes transactions.
+#[derive(Args, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct LedgerArgs {
+    /// Number of transactions a deposit can be disputed for.
+    ///
+    /// When set, deposits are evicted from memory once this many transactions have been processed
+    /// after them, and any later dispute against them is rejected. Deposits are retained forever
+    /// otherwise.
+    #[arg(
+        long,
+        value_name = "TRANSACTIONS",
+        conflicts_with = "dispute_window_duration"
+    )]
+    dispute_window: Option<u64>,
+
+    /// How long a deposit can be disputed for, e.g. 90days.
+    ///
+    /// The time-based counterpart of `--dispute-window`: timestamped deposits are evicted from
+    /// memory once a transaction is timestamped this long after them. Deposits without a timestamp
+    /// are retained forever.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(with = "config::duration")]
+    dispute_window_duration: Option<TimeDelta>,
+
+    /// How long a dispute can stay opened, e.g. 45days.
+    ///
+    /// When set, timestamped disputes are closed automatically as per `--expiry-policy` once a
+    /// transaction of the same client is timestamped this long after them. Disputes are also
+    /// expired at the end of the input, at `--as-of` or the latest timestamp of any client.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(with = "config::duration")]
+    dispute_expiry: Option<TimeDelta>,
+
+    /// How expired disputes are closed, either resolve or chargeback, resolve by default.
+    #[arg(long, value_name = "POLICY")]
+    expiry_policy: Option<ExpiryPolicy>,
+
+    /// Honors the amounts of disputes, resolves and chargebacks.
+    ///
+    /// When set, a deposit can be disputed partially, several times, up to its amount, and
+    /// disputes can be resolved or charged back partially. The amount is ignored otherwise.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    partial_disputes: Option<bool>,
+
+    /// Path to a TOML fee schedule.
+    ///
+    /// When set, the ledger charges the fees configured for each transaction type and posts them
+    /// to the house account. The total fees charged to each account are added to the output.
+    #[arg(long, value_name = "PATH")]
+    fees: Option<PathBuf>,
+
+    /// Path to a CSV of credit limits, with a client and a limit column.
+    ///
+    /// When set, withdrawals and transfers of the listed clients can overdraw their account down
+    /// to their limit. The limit and utilisation of each account are added to the output.
+    #[arg(long, value_name = "PATH")]
+    credit_limits: Option<PathBuf>,
+
+    /// Path to a TOML configuration of risk limits, globally or per client.
+    ///
+    /// When set, withdrawals and transfers breaching a limit are rejected, and the account is
+    /// locked if the limits say so.
+    #[arg(long, value_name = "PATH")]
+    risk_limits: Option<PathBuf>,
+
+    /// Rejects withdrawals and transfers from accounts opened this recently, e.g. 7days.
+    ///
+    /// An account is considered opened at its client's first timestamped transaction.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    #[serde(with = "config::duration")]
+    block_new_accounts: Option<TimeDelta>,
+
+    /// Flags deposits that are an exact multiple of this amount, e.g. 1000.
+    #[arg(long, value_name = "AMOUNT", value_parser = parse_amount)]
+    flag_round_deposits: Option<Amount>,
+
+    /// How many withdrawals and transfers in a row can be rejected before raising an alert, 3 by
+    /// default.
+    #[arg(long, value_name = "COUNT")]
+    alert_after_rejected_withdrawals: Option<u32>,
+}
+
+/// How the outcome of processing transactions is reported.
+#[derive(Args, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ReportArgs {
     /// Enables verbose output.
     ///
     /// When set (-v or --verbose), the application will print errors and warnings to stderr,
     /// otherwise it will ignore them silently.
-    #[arg(short, long)]
-    verbose: bool,
+    #[arg(
+        short, long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    verbose: Option<bool>,
+
+    /// Outputs the number of disputes, resolves and chargebacks applied to each account.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    dispute_counts: Option<bool>,
+
+    /// Path to a CSV the rejected transactions are written to, with the reason they were rejected.
+    #[arg(long, value_name = "PATH")]
+    rejects: Option<PathBuf>,
+
+    /// Path to a JSON lines file the alerts on suspicious activity are written to.
+    ///
+    /// Alerts are raised for disputes failing on insufficient funds, locked accounts, repeatedly
+    /// rejected withdrawals and transfers, and flagged transactions.
+    #[arg(long, value_name = "PATH")]
+    alerts: Option<PathBuf>,
+}
+
+/// How the input is read, when processing transactions.
+#[derive(Args, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct InputArgs {
+    /// How several inputs are merged, either concat, timestamp or tx, concat by default.
+    ///
+    /// concat reads the inputs one after the other, in order. timestamp and tx merge inputs each
+    /// sorted by timestamp or by transaction id, taking the earliest or lowest transaction first.
+    #[arg(long, value_name = "STRATEGY")]
+    merge: Option<MergeStrategy>,
+
+    /// Path to a TOML input dialect, for inputs in a partner's format.
+    ///
+    /// When set, the inputs are read with the dialect's delimiter, quoting, header row, column
+    /// names, decimal separator and transaction type aliases.
+    #[arg(long, value_name = "PATH")]
+    dialect: Option<PathBuf>,
+
+    /// Declares the input as sorted by client.
+    ///
+    /// When set, each account is output and dropped from memory as soon as its client's block of
+    /// transactions ends, only its id is kept. Transactions for a client that reappear after its
+    /// block ended are rejected. Cannot be used with `--as-of`.
+    #[arg(
+        long,
+        value_name = "BOOL",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "true"
+    )]
+    sorted: Option<bool>,
+
+    /// Path to a write-ahead log of the transactions applied, to recover from with `--resume`.
+    ///
+    /// When set, each transaction is logged before it's applied, and the state of the ledger is
+    /// checkpointed next to the log every `--checkpoint-every` transactions.
+    #[arg(long, value_name = "PATH")]
+    wal: Option<PathBuf>,
+
+    /// How many transactions are applied between checkpoints of the write-ahead log, 10000 by
+    /// default.
+    #[arg(long, value_name = "TRANSACTIONS")]
+    checkpoint_every: Option<u64>,
 }
 
 /// Petit Payments Engine (PPE).
@@ -37,38 +410,451 @@ struct Cli {
 /// The application will try to continue optimistically as best as it can, even on a malformed CSV it will try to
 /// process the valid lines. It will only return with err if the specified file can't be opened (i.e. does not exist),
 /// or if a buffer to stdout can't be flushed, which should never happen.
-fn main() -> Result<(), Box<dyn Error>> {
+fn main() -> Result<ExitCode, Box<dyn Error>> {
     // Parse command line options.
     let cli = Cli::parse();
-    if cli.verbose {
+    let file = ConfigFile::load(cli.config.as_deref())?;
+    if let Some(command) = cli.command {
+        return run(command, file);
+    }
+    let input = cli.input.or(file.input);
+    let ledger_args = cli.ledger.or(file.ledger);
+    let report_args = cli.report.or(file.report);
+    check_conflicts(&input, cli.since.is_some(), cli.as_of.is_some(), cli.resume);
+
+    let verbose = report_args.verbose == Some(true);
+    if verbose {
         eprintln!("Verbose mode enabled, printing all errors to stderr.");
     }
-    let rdr = create_csv_reader(cli.input_path)?;
+    let (txns, provenance) = read_inputs(&cli.input_paths, &input, verbose)?;
+    let txns = filter_as_of(txns, cli.as_of, cli.since.is_some(), verbose);
 
-    let txns = deserialize_transactions(Some(rdr), cli.verbose)?;
+    let mut ledger = create_ledger(&ledger_args)?;
+    let columns = output_columns(&ledger_args, report_args.dispute_counts == Some(true));
+    let mut reporter = create_reporter(report_args)?;
+    reporter.provenance = provenance;
 
-    let ledger = process_transactions(txns, cli.verbose);
+    if let Some(since) = cli.since {
+        let mut report = PeriodReport::new(since);
+        for txn in txns {
+            let result = report.apply(&mut ledger, txn);
+            reporter.report(&mut ledger, &txn, result);
+        }
+        expire_as_of(&mut ledger, cli.as_of, &mut reporter);
+
+        serialize_period_report(ledger, &report, None, verbose)?;
+    } else if let Some(path) = input.wal {
+        let checkpoint_every = input.checkpoint_every.unwrap_or(DEFAULT_CHECKPOINT_EVERY);
+        let (mut wal, recovery) = if cli.resume {
+            Wal::resume(&path, checkpoint_every, &mut ledger)?
+        } else {
+            (Wal::create(&path, checkpoint_every)?, Recovery::default())
+        };
+        let txns = recovery.skip(txns)?;
+        let mut ledger =
+            process_logged_transactions(ledger, txns, &mut reporter, &mut wal, recovery.offset)?;
+        expire_as_of(&mut ledger, cli.as_of, &mut reporter);
 
-    serialize_ledger(ledger, None, cli.verbose)?;
+        serialize_ledger(ledger, None, columns, verbose)?;
+    } else if input.sorted == Some(true) {
+        stream_ledger(ledger, txns, None, columns, &mut reporter)?;
+    } else {
+        let mut ledger = process_transactions(ledger, txns, &mut reporter);
+        expire_as_of(&mut ledger, cli.as_of, &mut reporter);
 
-    Ok(())
+        serialize_ledger(ledger, None, columns, verbose)?;
+    }
+
+    reporter.flush()?;
+    Ok(ExitCode::SUCCESS)
 }
 
-/// Process transactions
+/// Run a subcommand other than processing transactions, with the configuration `file`
+fn run(command: Command, file: ConfigFile) -> Result<ExitCode, Box<dyn Error>> {
+    match command {
+        Command::Diff {
+            old,
+            new,
+            tolerance,
+        } => diff(&old, &new, tolerance),
+        Command::Serve {
+            bind,
+            ledger,
+            report,
+        } => serve(&bind, &ledger.or(file.ledger), report.or(file.report)),
+        Command::Listen {
+            tcp,
+            #[cfg(unix)]
+            unix,
+            ledger,
+            report,
+        } => listen(
+            tcp.as_deref(),
+            #[cfg(unix)]
+            unix.as_deref(),
+            &ledger.or(file.ledger),
+            report.or(file.report),
+        ),
+        Command::Repl {
+            input_path,
+            verbose,
+            dispute_counts,
+            ledger,
+        } => repl(
+            input_path,
+            verbose.or(file.report.verbose).unwrap_or_default(),
+            dispute_counts
+                .or(file.report.dispute_counts)
+                .unwrap_or_default(),
+            &ledger.or(file.ledger),
+        ),
+        Command::Config {
+            command:
+                ConfigCommand::Show {
+                    input,
+                    ledger,
+                    report,
+                },
+        } => show_config(ConfigFile {
+            ledger: ledger.or(file.ledger),
+            report: report.or(file.report),
+            input: input.or(file.input),
+        }),
+    }
+}
+
+/// Check the options that conflict once merged with the configuration file
 ///
-/// Create a new ledger and apply all `txns` in the provider iterator. Returns the fully processed
-/// ledger.
-pub fn process_transactions(txns: impl Iterator<Item = Transaction>, verbose: bool) -> Ledger {
+/// The command line alone is checked as it's parsed, but options set in the file are only known
+/// afterwards. Exits with a usage error on a conflict.
+fn check_conflicts(input: &InputArgs, since: bool, as_of: bool, resume: bool) {
+    let sorted = input.sorted == Some(true);
+    let conflict = if input.wal.is_some() && sorted {
+        Some("--wal cannot be used with --sorted")
+    } else if input.wal.is_some() && since {
+        Some("--wal cannot be used with --since")
+    } else if sorted && since {
+        Some("--since cannot be used with --sorted")
+    } else if sorted && as_of {
+        Some("--as-of cannot be used with --sorted")
+    } else if resume && input.wal.is_none() {
+        Some("--resume requires --wal")
+    } else {
+        None
+    };
+    if let Some(conflict) = conflict {
+        Cli::command()
+            .error(ErrorKind::ArgumentConflict, conflict)
+            .exit();
+    }
+}
+
+/// Read the transactions of the inputs, or stdin if there are none
+///
+/// Inputs are read in the dialect of `args`, and several of them are merged as per its strategy,
+/// along with the provenance of their transactions.
+fn read_inputs(
+    patterns: &[String],
+    args: &InputArgs,
+    verbose: bool,
+) -> anyhow::Result<(Box<dyn Iterator<Item = Transaction>>, Option<Provenance>)> {
+    let dialect = args
+        .dialect
+        .as_deref()
+        .map(read_dialect)
+        .transpose()?
+        .unwrap_or_default();
+    let paths = expand_input_paths(patterns)?;
+    if paths.is_empty() {
+        let rdr = create_dialect_reader(None, &dialect)?;
+        return Ok((
+            Box::new(deserialize_dialect_transactions(
+                Some(rdr),
+                &dialect,
+                verbose,
+            )?),
+            None,
+        ));
+    }
+    let merge = args.merge.unwrap_or_default();
+    let (txns, provenance) = merge_inputs(&paths, merge, &dialect, verbose)?;
+    Ok((Box::new(txns), (paths.len() > 1).then_some(provenance)))
+}
+
+/// Create a ledger configured as per the command line
+fn create_ledger(args: &LedgerArgs) -> anyhow::Result<Ledger> {
     let mut ledger = Ledger::default();
+    ledger.dispute_window = match (args.dispute_window, args.dispute_window_duration) {
+        (Some(_), Some(_)) => {
+            bail!("dispute_window cannot be used with dispute_window_duration")
+        }
+        (Some(window), None) => Some(DisputeWindow::Transactions(window)),
+        (None, window) => window.map(DisputeWindow::Duration),
+    };
+    ledger.dispute_expiry = args.dispute_expiry.map(|after| DisputeExpiry {
+        after,
+        policy: args.expiry_policy.unwrap_or_default(),
+    });
+    ledger.partial_disputes = args.partial_disputes == Some(true);
+    ledger.fee_schedule = args.fees.as_deref().map(read_fee_schedule).transpose()?;
+    if let Some(path) = &args.credit_limits {
+        ledger.credit_limits = read_credit_limits(path)?;
+    }
+    if let Some(path) = &args.risk_limits {
+        ledger.risk_limits = read_risk_limits(path)?;
+    }
+    if let Some(min_age) = args.block_new_accounts {
+        ledger
+            .validators
+            .push(Box::new(NewAccountOutflows::new(min_age)));
+    }
+    if let Some(multiple) = args.flag_round_deposits {
+        ledger.validators.push(Box::new(RoundDeposits { multiple }));
+    }
+    ledger.rejected_withdrawals_alert = Some(
+        args.alert_after_rejected_withdrawals
+            .unwrap_or(DEFAULT_ALERT_AFTER_REJECTED_WITHDRAWALS),
+    );
+    Ok(ledger)
+}
+
+/// Get the optional output columns for a ledger configured as per the command line
+fn output_columns(ledger: &LedgerArgs, dispute_counts: bool) -> OutputColumns {
+    OutputColumns {
+        dispute_counts,
+        fees: ledger.fees.is_some(),
+        credit: ledger.credit_limits.is_some(),
+    }
+}
+
+/// Create a reporter configured as per the command line
+fn create_reporter(args: ReportArgs) -> anyhow::Result<Reporter<'static>> {
+    let mut reporter = Reporter::new(args.verbose == Some(true));
+    reporter.rejects = args
+        .rejects
+        .map(|path| create_csv_writer(Some(path)))
+        .transpose()?;
+    reporter.alerts = args.alerts.as_deref().map(create_file_writer).transpose()?;
+    Ok(reporter)
+}
+
+/// Serve an HTTP/JSON API over a ledger on `bind`, until the process is interrupted or terminated
+///
+/// The reports are finished on the way out, so compressed ones are complete.
+fn serve(bind: &str, ledger: &LedgerArgs, report: ReportArgs) -> Result<ExitCode, Box<dyn Error>> {
+    let columns = output_columns(ledger, report.dispute_counts == Some(true));
+    let ledger = create_ledger(ledger)?;
+    let verbose = report.verbose == Some(true);
+    let server =
+        Arc::new(Server::http(bind).map_err(|e| anyhow!("failed to listen on {bind}: {e}"))?);
+    if verbose {
+        eprintln!("Serving on http://{}", server.server_addr());
+    }
+    {
+        let server = Arc::clone(&server);
+        ctrlc::set_handler(move || server.unblock())?;
+    }
+
+    LedgerServer {
+        ledger,
+        columns,
+        reporter: create_reporter(report)?,
+    }
+    .serve(&server)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Stream CSV rows from the connections to a socket into a ledger, until the process is interrupted
+/// or terminated
+///
+/// The reports are finished on the way out, so compressed ones are complete.
+fn listen(
+    tcp: Option<&str>,
+    #[cfg(unix)] unix: Option<&Path>,
+    ledger: &LedgerArgs,
+    report: ReportArgs,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let columns = output_columns(ledger, report.dispute_counts == Some(true));
+    let ledger = create_ledger(ledger)?;
+    let verbose = report.verbose == Some(true);
+    let (requests, received) = mpsc::channel();
+
+    if let Some(addr) = tcp {
+        let listener = TcpListener::bind(addr)?;
+        if verbose {
+            eprintln!("Listening on {}", listener.local_addr()?);
+        }
+        accept(
+            move || listener.accept().map(|(stream, _)| stream),
+            requests.clone(),
+        );
+    }
+    #[cfg(unix)]
+    if let Some(path) = unix {
+        let listener = UnixListener::bind(path)?;
+        if verbose {
+            eprintln!("Listening on {}", path.display());
+        }
+        accept(
+            move || listener.accept().map(|(stream, _)| stream),
+            requests.clone(),
+        );
+    }
+    ctrlc::set_handler(move || {
+        let (reply, _) = mpsc::channel();
+        let _ = requests.send(Request {
+            command: line::Command::Stop,
+            reply,
+        });
+    })?;
 
-    // Then process all transactions.
+    LineServer {
+        ledger,
+        columns,
+        reporter: create_reporter(report)?,
+    }
+    .serve(&received)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Explore a ledger interactively on stdin and stdout, until the input ends or the session is quit
+fn repl(
+    input_path: Option<PathBuf>,
+    verbose: bool,
+    dispute_counts: bool,
+    ledger: &LedgerArgs,
+) -> Result<ExitCode, Box<dyn Error>> {
+    let columns = output_columns(ledger, dispute_counts);
+    let mut repl = Repl::new(|| create_ledger(ledger), columns, verbose)?;
+    if let Some(path) = input_path {
+        let txns = deserialize_transactions(Some(create_csv_reader(Some(path))?), verbose)?;
+        println!(
+            "loaded {} transactions, type help for the commands",
+            repl.load(txns)
+        );
+    }
+    repl.run(stdin().lock(), stdout())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Print the effective configuration to stdout, with the default of every option that has one
+fn show_config(config: ConfigFile) -> Result<ExitCode, Box<dyn Error>> {
+    print!("{}", toml::to_string_pretty(&config.with_defaults())?);
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Compare two ledger outputs
+///
+/// Writes the differences to stdout, and fails with code 1 if there are any, or with code 2 if an
+/// output can't be read, like diff(1).
+fn diff(old: &Path, new: &Path, tolerance: Amount) -> Result<ExitCode, Box<dyn Error>> {
+    let read = |path: &Path| {
+        File::open(path)
+            .and_then(|file| decompress(BufReader::new(file)))
+            .map_err(anyhow::Error::from)
+            .and_then(read_ledger)
+            .with_context(|| format!("failed to read {}", path.display()))
+    };
+    let (old, new) = match (read(old), read(new)) {
+        (Ok(old), Ok(new)) => (old, new),
+        (Err(e), _) | (_, Err(e)) => {
+            eprintln!("Error: {e:?}");
+            return Ok(ExitCode::from(2));
+        }
+    };
+    let differences = diff_ledgers(&old, &new, tolerance);
+    write_differences(stdout(), &differences)?;
+    Ok(if differences.is_empty() {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    })
+}
+
+/// Process transactions
+///
+/// Apply all `txns` in the provider iterator on top of `ledger`. Returns the fully processed
+/// ledger.
+pub fn process_transactions(
+    mut ledger: Ledger,
+    txns: impl Iterator<Item = Transaction>,
+    reporter: &mut Reporter<'_>,
+) -> Ledger {
+    // Process all transactions.
     txns.for_each(|txn| {
-        if let Err(e) = ledger.apply(txn) {
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+    });
+
+    ledger
+}
+
+/// Process transactions through a write-ahead log
+///
+/// Logs each of `txns` to `wal` before applying it on top of `ledger`, `offset` being the number
+/// of input transactions already applied to it. Returns the fully processed ledger, once
+/// checkpointed.
+fn process_logged_transactions(
+    mut ledger: Ledger,
+    txns: impl Iterator<Item = Transaction>,
+    reporter: &mut Reporter<'_>,
+    wal: &mut Wal,
+    mut offset: u64,
+) -> anyhow::Result<Ledger> {
+    for txn in txns {
+        wal.log(offset, &txn)?;
+        let result = ledger.apply(txn);
+        reporter.report(&mut ledger, &txn, result);
+        offset += 1;
+        wal.commit(offset, &ledger)?;
+    }
+    wal.checkpoint(offset, &ledger)?;
+
+    Ok(ledger)
+}
+
+/// Filter transactions up to an instant
+///
+/// Keeps the `txns` timestamped up to `as_of`. Transactions without a timestamp can't be placed in
+/// time, so they are dropped whenever a time query is requested, i.e. `as_of` is set or `timed`.
+fn filter_as_of(
+    txns: impl Iterator<Item = Transaction>,
+    as_of: Option<Timestamp>,
+    timed: bool,
+    verbose: bool,
+) -> impl Iterator<Item = Transaction> {
+    let timed = timed || as_of.is_some();
+    txns.filter(move |txn| match txn.timestamp {
+        Some(timestamp) => as_of.is_none_or(|as_of| timestamp <= as_of),
+        None if timed => {
             if verbose {
-                eprintln!("warning: {e}");
+                eprintln!("warning: ignoring {:?} without a timestamp", txn.tx);
             }
+            false
         }
-    });
+        None => true,
+    })
+}
 
-    ledger
+/// Expire the disputes of all accounts at `as_of`, or at the latest timestamp of the ledger
+///
+/// Otherwise a dispute would only expire with a later transaction of the same client, so the last
+/// dispute of a client would stay opened regardless of the rest of the input.
+fn expire_as_of(ledger: &mut Ledger, as_of: Option<Timestamp>, reporter: &mut Reporter<'_>) {
+    if let Some(as_of) = as_of.or_else(|| ledger.latest_timestamp()) {
+        ledger.expire_disputes(as_of);
+        reporter.report_ledger(ledger);
+    }
+}
+
+/// Parse a non-negative decimal amount, e.g. 1000
+fn parse_amount(s: &str) -> Result<Amount, String> {
+    let d = Decimal::from_str(s).map_err(|e| e.to_string())?;
+    Amount::try_from(d).map_err(|e| e.to_string())
+}
+
+/// Parse a human readable duration, e.g. 45days
+fn parse_duration(s: &str) -> Result<TimeDelta, String> {
+    let duration = humantime::parse_duration(s).map_err(|e| e.to_string())?;
+    TimeDelta::from_std(duration).map_err(|e| e.to_string())
 }
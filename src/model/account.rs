@@ -2,13 +2,18 @@ use std::collections::HashMap;
 
 use super::{
     balance::Balance,
-    common::{Client, Tx},
-    transaction::{Deposit, Transaction, Type},
+    common::{Amount, Client, Timestamp, Tx},
+    ledger::{DisputeExpiry, ExpiryPolicy},
+    risk::{Outflows, RiskLimits},
+    transaction::{Deposit, DisputeEvent, DisputeStatus, Transaction, Transfer, Type, Withdrawal},
 };
 use anyhow::{Result, anyhow, bail};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
 /// A client's account.
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Account {
     /// The client who owns the account.
     pub client: Client,
@@ -18,6 +23,58 @@ pub struct Account {
     pub locked: bool,
     /// A set of deposits (as a `HashMap` for fast random access using the `Tx`)
     pub deposits: HashMap<Tx, Deposit>,
+    /// The highest `Tx` among the deposits evicted after their dispute window, if any.
+    ///
+    /// Transaction ids are assumed to increase for each client, so every missing deposit up to it
+    /// is taken as evicted, see `evict`.
+    pub evicted_through: Option<Tx>,
+    /// The timestamp of the latest timestamped transaction applied to this account, if any.
+    pub last_timestamp: Option<Timestamp>,
+    /// The instant each currently opened dispute was opened at, for timestamped disputes.
+    pub opened_disputes: HashMap<Tx, Timestamp>,
+    /// The dispute lifecycle transitions of each disputed deposit, in the order they happened.
+    pub dispute_history: HashMap<Tx, Vec<DisputeEvent>>,
+    /// The number of dispute lifecycle transitions on this account, by kind.
+    pub dispute_counts: DisputeCounts,
+    /// The fees charged to this account.
+    pub fees: Amount,
+    /// How far withdrawals and transfers can overdraw this account, zero by default.
+    pub credit_limit: Amount,
+    /// The risk limits on the withdrawals and transfers of this account, if any.
+    pub risk_limits: Option<RiskLimits>,
+    /// The recent withdrawals and transfers of this account, to check the risk limits.
+    pub outflows: Outflows,
+}
+
+/// The number of dispute lifecycle transitions on an account, by kind.
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct DisputeCounts {
+    /// Disputes applied, including partial ones
+    pub disputes: u64,
+    /// Resolves applied, including partial ones
+    pub resolves: u64,
+    /// Chargebacks applied
+    pub chargebacks: u64,
+}
+
+/// A dispute-family transaction references a deposit that has been evicted after its dispute
+/// window.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("deposit {0:?} is past its dispute window")]
+pub struct DisputeWindowExpiredError(pub Tx);
+
+/// A transaction targets a locked account.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("account is locked {}", .0.0)]
+pub struct AccountLockedError(pub Client);
+
+/// A transaction is timestamped earlier than the latest transaction of the same client.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{tx:?} at {timestamp} is earlier than the latest transaction at {last}")]
+pub struct NonMonotonicTimestampError {
+    pub tx: Tx,
+    pub timestamp: Timestamp,
+    pub last: Timestamp,
 }
 
 impl Account {
@@ -32,6 +89,15 @@ impl Account {
             balance: Balance::default(),
             locked: false,
             deposits: HashMap::new(),
+            evicted_through: None,
+            last_timestamp: None,
+            opened_disputes: HashMap::new(),
+            dispute_history: HashMap::new(),
+            dispute_counts: DisputeCounts::default(),
+            fees: Amount::ZERO,
+            credit_limit: Amount::ZERO,
+            risk_limits: None,
+            outflows: Outflows::default(),
         }
     }
 
@@ -54,59 +120,204 @@ impl Account {
     /// `apply` will always fail if the account is locked.
     ///
     /// `apply` will always fail for transactions belonging to a different client.
+    ///
+    /// `apply` will always fail for transactions timestamped earlier than the latest timestamped
+    /// transaction of this account. The timestamp of any other transaction is only recorded once
+    /// the transaction has been applied.
+    ///
+    /// Withdrawals and transfers fail with a `RiskLimitError` if they breach the account's risk
+    /// limits, locking the account if the limits say so.
     pub fn apply(&mut self, transaction: Transaction) -> Result<()> {
         Account::bail_if_unauthorized(self, transaction.client)?;
         Account::bail_if_locked(self)?;
+        Account::bail_if_out_of_order(self, &transaction)?;
+        self.try_apply(transaction)?;
+        if transaction.timestamp.is_some() {
+            self.last_timestamp = transaction.timestamp;
+        }
+        Ok(())
+    }
 
+    fn try_apply(&mut self, transaction: Transaction) -> Result<()> {
         match transaction.t_type {
             Type::Deposit(deposit) => {
-                self.balance.credit(deposit.amount);
+                self.balance.credit(deposit.amount).map_err(|e| {
+                    let context = format!("{e} for tx {:?}", transaction.tx);
+                    anyhow::Error::new(e).context(context)
+                })?;
                 self.deposits.insert(transaction.tx, deposit);
                 Ok(())
             }
-            Type::Withdrawal(withdrawal) => self
-                .balance
-                .debit(withdrawal.amount)
-                .map_err(|e| anyhow!("{e} for tx {:?}", transaction.tx)),
-            Type::Dispute => {
-                let deposit = self.get_deposit(transaction.tx)?;
-                let amount = deposit.amount;
-
-                deposit
-                    .open_dispute()
+            Type::Withdrawal(Withdrawal { amount }) | Type::Transfer(Transfer { amount, .. }) => {
+                self.bail_if_risky(&transaction, amount)?;
+                self.balance
+                    .debit_with_limit(amount, self.credit_limit)
+                    .map_err(|e| {
+                        let context = format!("{e} for tx {:?}", transaction.tx);
+                        anyhow::Error::new(e).context(context)
+                    })?;
+                if let Some(limits) = self.risk_limits {
+                    limits.record(&mut self.outflows, amount, transaction.timestamp);
+                }
+                Ok(())
+            }
+            Type::Dispute(amount) => {
+                let amount = Account::dispute_amount(&transaction, amount)?;
+                let mut deposit = *self.get_deposit(transaction.tx)?;
+                let amount = deposit
+                    .open_dispute(amount)
                     .map_err(|e| anyhow!("{e} for {:?}", transaction.tx))?;
 
-                self.balance.hold(amount)
-
-                // FIXME: I think we should lock the account here if hold().is_err(); as this looks like a
-                // typical case of fraud, but I don't want to break the spec without discussing it
-                // with whomever is going to consume this.
+                // We don't lock the account if hold().is_err(), even if it looks like a typical
+                // case of fraud, as it would break the spec. The ledger raises an alert instead.
+                self.balance.hold(amount)?;
+                self.deposits.insert(transaction.tx, deposit);
+                if let Some(timestamp) = transaction.timestamp {
+                    self.opened_disputes
+                        .entry(transaction.tx)
+                        .or_insert(timestamp);
+                }
+                self.record_dispute_event(&transaction, deposit.dispute_status, amount);
+                Ok(())
             }
-            Type::Resolve => {
-                let deposit = self.get_deposit(transaction.tx)?;
-                let amount = deposit.amount;
-
-                deposit
-                    .close_dispute()
+            Type::Resolve(amount) => {
+                let amount = Account::dispute_amount(&transaction, amount)?;
+                let mut deposit = *self.get_deposit(transaction.tx)?;
+                let amount = deposit
+                    .resolve_dispute(amount)
                     .map_err(|e| anyhow!("{e} for {:?}", transaction.tx))?;
 
-                self.balance.release(amount)
+                self.balance.release(amount)?;
+                self.deposits.insert(transaction.tx, deposit);
+                if !deposit.is_disputed() {
+                    self.opened_disputes.remove(&transaction.tx);
+                }
+                self.record_dispute_event(&transaction, deposit.dispute_status, amount);
+                Ok(())
             }
-            Type::Chargeback => {
+            Type::Chargeback(amount) => {
+                let amount = Account::dispute_amount(&transaction, amount)?;
                 self.lock();
-                let deposit = self.get_deposit(transaction.tx)?;
-                let amount = deposit.amount;
-
-                deposit
-                    .close_dispute()
+                let mut deposit = *self.get_deposit(transaction.tx)?;
+                let (amount, released) = deposit
+                    .charge_back(amount)
                     .map_err(|e| anyhow!("{e} for {:?}", transaction.tx))?;
 
-                self.balance.reimburse(amount)
+                self.balance.reimburse(amount)?;
+                self.balance.release(released)?;
+                self.deposits.insert(transaction.tx, deposit);
+                self.opened_disputes.remove(&transaction.tx);
+                self.record_dispute_event(&transaction, deposit.dispute_status, amount);
+                Ok(())
             }
         }
     }
 
+    /// Check an incoming transfer
+    ///
+    /// Returns the balance this account would have after receiving `amount`, without modifying
+    /// it, so the transfer can be committed once the sender has been debited.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the account is locked, or if its balance can't hold `amount`
+    /// more funds.
+    pub fn check_incoming(&self, amount: Amount) -> Result<Balance> {
+        Account::bail_if_locked(self)?;
+        let mut balance = self.balance;
+        balance.credit(amount)?;
+        Ok(balance)
+    }
+
+    /// Expire disputes
+    ///
+    /// Closes every dispute opened for longer than `expiry.after` at `now`, by applying a synthetic
+    /// resolve or chargeback as per `expiry.policy`. The synthetic transactions are timestamped at
+    /// the dispute's deadline, and returned in deadline order once applied.
+    ///
+    /// Synthetic transactions that fail, e.g. because the account is locked, leave their dispute
+    /// opened and are not returned.
+    pub fn expire_disputes(&mut self, expiry: DisputeExpiry, now: Timestamp) -> Vec<Transaction> {
+        self.expired_disputes(expiry, now)
+            .into_iter()
+            .filter(|txn| self.apply(*txn).is_ok())
+            .collect()
+    }
+
+    /// Get the synthetic transactions that would close the disputes expired at `now`
+    ///
+    /// They are returned in deadline order, without being applied, see `expire_disputes`.
+    #[must_use]
+    pub fn expired_disputes(&self, expiry: DisputeExpiry, now: Timestamp) -> Vec<Transaction> {
+        let mut expired: Vec<(Timestamp, Tx)> = self
+            .opened_disputes
+            .iter()
+            .filter_map(|(tx, opened)| {
+                let deadline = opened.0.checked_add_signed(expiry.after)?;
+                Some((Timestamp(deadline), *tx))
+            })
+            .filter(|(deadline, _)| *deadline <= now)
+            .collect();
+        expired.sort_unstable();
+
+        let client = self.client;
+        expired
+            .into_iter()
+            .map(|(deadline, tx)| Transaction {
+                t_type: match expiry.policy {
+                    ExpiryPolicy::Resolve => Type::Resolve(None),
+                    ExpiryPolicy::Chargeback => Type::Chargeback(None),
+                },
+                client,
+                tx,
+                timestamp: Some(deadline),
+            })
+            .collect()
+    }
+
+    /// Evict a deposit
+    ///
+    /// Drops the deposit for `tx`, along with its dispute history, so it no longer takes up memory.
+    /// Disputes, resolves and chargebacks against it are rejected with a
+    /// `DisputeWindowExpiredError` from then on.
+    ///
+    /// Only the highest evicted `Tx` is kept, rather than every one of them, so memory stays flat.
+    /// This assumes the transaction ids of a client increase as its deposits are applied: a dispute
+    /// against a missing deposit with a `Tx` up to the highest evicted one is reported as expired,
+    /// even if the deposit never existed.
+    pub fn evict(&mut self, tx: Tx) {
+        if self.deposits.remove(&tx).is_some() {
+            self.dispute_history.remove(&tx);
+            self.evicted_through = self.evicted_through.max(Some(tx));
+        }
+    }
+
+    fn record_dispute_event(
+        &mut self,
+        transaction: &Transaction,
+        status: DisputeStatus,
+        amount: Amount,
+    ) {
+        match transaction.t_type {
+            Type::Dispute(_) => self.dispute_counts.disputes += 1,
+            Type::Resolve(_) => self.dispute_counts.resolves += 1,
+            Type::Chargeback(_) => self.dispute_counts.chargebacks += 1,
+            Type::Deposit(_) | Type::Withdrawal(_) | Type::Transfer(_) => return,
+        }
+        self.dispute_history
+            .entry(transaction.tx)
+            .or_default()
+            .push(DisputeEvent {
+                status,
+                amount,
+                timestamp: transaction.timestamp,
+            });
+    }
+
     fn get_deposit(&mut self, tx: Tx) -> Result<&mut Deposit> {
+        if !self.deposits.contains_key(&tx) && self.evicted_through.is_some_and(|e| tx <= e) {
+            return Err(DisputeWindowExpiredError(tx).into());
+        }
         self.deposits
             .get_mut(&tx)
             .ok_or(anyhow!("deposit missing tx: {:?}", tx))
@@ -114,7 +325,54 @@ impl Account {
 
     fn bail_if_locked(&self) -> Result<()> {
         if self.locked {
-            bail!("account is locked {}", self.client.0)
+            return Err(AccountLockedError(self.client).into());
+        }
+        Ok(())
+    }
+
+    /// Check the amount of a partial dispute, resolve or chargeback, if any
+    fn dispute_amount(
+        transaction: &Transaction,
+        amount: Option<Decimal>,
+    ) -> Result<Option<Amount>> {
+        amount
+            .map(|amount| {
+                Amount::try_from(amount).map_err(|e| {
+                    let context = format!("{e} for tx {:?}", transaction.tx);
+                    anyhow::Error::new(e).context(context)
+                })
+            })
+            .transpose()
+    }
+
+    fn bail_if_out_of_order(&self, transaction: &Transaction) -> Result<()> {
+        if let (Some(timestamp), Some(last)) = (transaction.timestamp, self.last_timestamp)
+            && timestamp < last
+        {
+            return Err(NonMonotonicTimestampError {
+                tx: transaction.tx,
+                timestamp,
+                last,
+            }
+            .into());
+        }
+        Ok(())
+    }
+
+    fn bail_if_risky(&mut self, transaction: &Transaction, amount: Amount) -> Result<()> {
+        let Some(limits) = self.risk_limits else {
+            return Ok(());
+        };
+        if let Err(e) = limits.check(
+            &self.outflows,
+            transaction.tx,
+            amount,
+            transaction.timestamp,
+        ) {
+            if limits.lock_on_breach {
+                self.lock();
+            }
+            return Err(e.into());
         }
         Ok(())
     }
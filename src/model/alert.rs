@@ -0,0 +1,41 @@
+use serde::Serialize;
+
+use super::common::{Client, Tx};
+
+/// A suspicious event raised by a `Ledger` while applying a transaction
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Alert {
+    /// The client the event happened to
+    pub client: Client,
+    /// The transaction that raised the event
+    pub tx: Tx,
+    /// The rule that raised the event
+    pub rule: AlertRule,
+    /// How serious the event is
+    pub severity: Severity,
+}
+
+/// The rules that raise alerts
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlertRule {
+    /// A dispute failed because the disputed funds were no longer available, e.g. withdrawn
+    DisputeInsufficientFunds,
+    /// An account was locked, e.g. by a chargeback or a risk limit breach
+    AccountLocked,
+    /// Several withdrawals or transfers in a row were rejected
+    RepeatedRejectedWithdrawals,
+    /// A validator flagged the transaction
+    Flagged,
+    /// The fee of a transaction was waived because the house account is locked or finalized
+    FeeWaived,
+}
+
+/// How serious an alert is
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Severity {
+    Info,
+    Warning,
+    Critical,
+}
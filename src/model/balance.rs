@@ -1,16 +1,69 @@
-use super::common::Amount;
-use anyhow::{Result, bail};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::common::{Amount, AmountError, SignedAmount};
 
 /// The balance of an account.
 ///
 /// Balances can only be created by default, and their internal fields can only be modified through
 /// their public API, which ensures validations apply.
-#[derive(Debug)]
+///
+/// All operations keep the `total` representable as an `Amount`, so reading it never overflows.
+///
+/// Funds can only be overdrawn through `debit_with_limit`, in which case `available` stays at zero
+/// and the debt is tracked in `overdrawn`, which is repaid before any funds become available again.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+#[serde(try_from = "UncheckedBalance")]
 pub struct Balance {
     /// The available funds.
     available: Amount,
     /// The funds on hold.
     held: Amount,
+    /// The funds drawn beyond `available`, against a credit limit.
+    overdrawn: Amount,
+}
+
+/// A deserialized balance, before checking its `total` is representable
+#[derive(Deserialize)]
+struct UncheckedBalance {
+    available: Amount,
+    held: Amount,
+    overdrawn: Amount,
+}
+
+impl TryFrom<UncheckedBalance> for Balance {
+    type Error = BalanceError;
+
+    fn try_from(balance: UncheckedBalance) -> Result<Self, BalanceError> {
+        balance.available.checked_add(balance.held)?;
+        Ok(Self {
+            available: balance.available,
+            held: balance.held,
+            overdrawn: balance.overdrawn,
+        })
+    }
+}
+
+/// The ways an operation on a `Balance` can fail.
+///
+/// A failed operation never modifies the balance.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum BalanceError {
+    #[error("insufficient funds")]
+    InsufficientFunds,
+    #[error("insufficient funds on hold")]
+    InsufficientFundsOnHold,
+    #[error("the balance would overflow")]
+    Overflow,
+}
+
+impl From<AmountError> for BalanceError {
+    fn from(e: AmountError) -> Self {
+        match e {
+            AmountError::Overflow => BalanceError::Overflow,
+            AmountError::Underflow => BalanceError::InsufficientFunds,
+        }
+    }
 }
 
 impl Default for Balance {
@@ -19,17 +72,38 @@ impl Default for Balance {
         Self {
             available: Amount::ZERO,
             held: Amount::ZERO,
+            overdrawn: Amount::ZERO,
         }
     }
 }
 
 impl Balance {
     /// The currently available funds
+    ///
+    /// Zero while the balance is overdrawn, see `signed_available`.
     #[must_use]
     pub fn available(&self) -> Amount {
         self.available
     }
 
+    /// The funds currently drawn against a credit limit
+    #[must_use]
+    pub fn overdrawn(&self) -> Amount {
+        self.overdrawn
+    }
+
+    /// The currently available funds, negative while overdrawn
+    #[must_use]
+    pub fn signed_available(&self) -> SignedAmount {
+        self.available.signed_sub(self.overdrawn)
+    }
+
+    /// The total funds, negative while overdrawn beyond the funds on hold
+    #[must_use]
+    pub fn signed_total(&self) -> SignedAmount {
+        self.total().signed_sub(self.overdrawn)
+    }
+
     /// The funds currently on hold
     #[must_use]
     pub fn held(&self) -> Amount {
@@ -38,17 +112,28 @@ impl Balance {
 
     /// The total funds
     ///
-    /// Returns the total balance regardless of status, i.e. `available` + `held`.
+    /// Returns the total balance regardless of status, i.e. `available` + `held`, without
+    /// accounting for the `overdrawn` funds, see `signed_total`.
+    ///
+    /// # Panics
+    ///
+    /// Never, as `credit` refuses any amount that would make the total unrepresentable.
     #[must_use]
     pub fn total(&self) -> Amount {
-        self.available + self.held
+        self.available
+            .checked_add(self.held)
+            .expect("credit keeps the total representable")
     }
 
     /// Perform a credit
     ///
-    /// Adds `amount` funds to the available balance.
-    pub fn credit(&mut self, amount: Amount) {
-        self.available += amount;
+    /// Adds `amount` funds to the available balance, repaying any overdrawn funds first.
+    ///
+    /// # Errors
+    /// `BalanceError::Overflow` if the total balance can't hold `amount` more funds.
+    pub fn credit(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        self.total().checked_add(amount)?;
+        self.add_available(amount)
     }
 
     /// Perform a debit
@@ -56,14 +141,33 @@ impl Balance {
     /// Subs `amount` funds from the available balance, if there's sufficient funds.
     ///
     /// # Errors
-    /// `anyhow::Error` on insufficient funds.
-    pub fn debit(&mut self, amount: Amount) -> Result<()> {
-        if self.available >= amount {
-            self.available -= amount;
-            Ok(())
-        } else {
-            bail!("insufficient funds")
+    /// `BalanceError::InsufficientFunds` on insufficient funds.
+    pub fn debit(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        self.available = self.available.checked_sub(amount)?;
+        Ok(())
+    }
+
+    /// Perform a debit against a credit limit
+    ///
+    /// Subs `amount` funds from the available balance, overdrawing it by up to `limit` funds in
+    /// total if there's insufficient funds. With a zero `limit` it behaves like `debit`.
+    ///
+    /// # Errors
+    /// `BalanceError::InsufficientFunds` if the debit would exceed the credit limit.
+    pub fn debit_with_limit(&mut self, amount: Amount, limit: Amount) -> Result<(), BalanceError> {
+        if let Ok(available) = self.available.checked_sub(amount) {
+            self.available = available;
+            return Ok(());
         }
+        let overdrawn = self
+            .overdrawn
+            .checked_add(amount.checked_sub(self.available)?)?;
+        if overdrawn > limit {
+            return Err(BalanceError::InsufficientFunds);
+        }
+        self.available = Amount::ZERO;
+        self.overdrawn = overdrawn;
+        Ok(())
     }
 
     /// Put funds on hold
@@ -71,31 +175,29 @@ impl Balance {
     /// Moves `amount` funds from available to on hold, if there's sufficient funds.
     ///
     /// # Errors
-    /// `anyhow::Error` on inssuficient funds.
-    pub fn hold(&mut self, amount: Amount) -> Result<()> {
-        if self.available >= amount {
-            self.available -= amount;
-            self.held += amount;
-            Ok(())
-        } else {
-            bail!("insufficient funds")
-        }
+    /// `BalanceError::InsufficientFunds` on inssuficient funds.
+    pub fn hold(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        let available = self.available.checked_sub(amount)?;
+        self.held = self.held.checked_add(amount)?;
+        self.available = available;
+        Ok(())
     }
 
     /// Release funds from hold
     ///
-    /// Moves `amount` funds from `held` to `available`, if there's sufficient funds on hold.
+    /// Moves `amount` funds from `held` to `available`, if there's sufficient funds on hold. Any
+    /// overdrawn funds are repaid first.
     ///
     /// # Errors
-    /// `anyhow::Error` on inssuficient funds on hold.
-    pub fn release(&mut self, amount: Amount) -> Result<()> {
-        if self.held >= amount {
-            self.held -= amount;
-            self.available += amount;
-            Ok(())
-        } else {
-            bail!("insufficient funds on hold")
-        }
+    /// `BalanceError::InsufficientFundsOnHold` on inssuficient funds on hold.
+    pub fn release(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        let held = self
+            .held
+            .checked_sub(amount)
+            .or(Err(BalanceError::InsufficientFundsOnHold))?;
+        self.add_available(amount)?;
+        self.held = held;
+        Ok(())
     }
 
     /// Reimburse held funds
@@ -103,13 +205,28 @@ impl Balance {
     /// Substracts `amount` funds from `held`, if there's sufficient funds on hold.
     ///
     /// # Errors
-    /// `anyhow::Error` on inssuficient funds on hold.
-    pub fn reimburse(&mut self, amount: Amount) -> Result<()> {
-        if self.held >= amount {
-            self.held -= amount;
-            Ok(())
-        } else {
-            bail!("insufficient funds on hold")
+    /// `BalanceError::InsufficientFundsOnHold` on inssuficient funds on hold.
+    pub fn reimburse(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        self.held = self
+            .held
+            .checked_sub(amount)
+            .or(Err(BalanceError::InsufficientFundsOnHold))?;
+        Ok(())
+    }
+
+    fn add_available(&mut self, amount: Amount) -> Result<(), BalanceError> {
+        if self.overdrawn == Amount::ZERO {
+            self.available = self.available.checked_add(amount)?;
+            return Ok(());
         }
+        let repaid = if amount < self.overdrawn {
+            amount
+        } else {
+            self.overdrawn
+        };
+        let available = self.available.checked_add(amount.checked_sub(repaid)?)?;
+        self.overdrawn = self.overdrawn.checked_sub(repaid)?;
+        self.available = available;
+        Ok(())
     }
 }
@@ -1,29 +1,87 @@
-use std::ops::{Add, AddAssign, Sub, SubAssign};
+use std::{fmt::Display, str::FromStr};
 
+use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-/// The client id
+/// The client id, any unsigned 64-bit integer
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
-pub struct Client(pub u16);
+pub struct Client(pub u64);
 
-/// The transaction id
-#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize)]
-pub struct Tx(pub u32);
+/// The transaction id, any unsigned 64-bit integer
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Tx(pub u64);
+
+/// The instant a transaction happened at
+///
+/// It can be parsed from an RFC 3339 string, e.g. `2026-09-30T23:59:59Z`.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Serialize, Deserialize)]
+pub struct Timestamp(pub DateTime<Utc>);
+
+impl FromStr for Timestamp {
+    type Err = chrono::ParseError;
+
+    /// Parse from an RFC 3339 string, in any offset.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(Timestamp(
+            DateTime::parse_from_rfc3339(s)?.with_timezone(&Utc),
+        ))
+    }
+}
+
+impl Display for Timestamp {
+    /// Format as an RFC 3339 string.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.to_rfc3339())
+    }
+}
 
 /// An ad-hoc type that represents non-negative, arbitrary precision unitless amounts.
 ///
-/// Besides derivations, it supports add, sub, addassign and subassign. It can be constructed by
-/// `try_from` a decimal.
+/// Arithmetic is only exposed through `checked_add` and `checked_sub`, which never panic nor clip,
+/// and report overflows and underflows as an `AmountError`. It can be constructed by `try_from` a
+/// decimal.
 #[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Serialize, Deserialize)]
 #[serde(try_from = "rust_decimal::Decimal")]
 pub struct Amount(Decimal);
 
+/// A signed, arbitrary precision unitless amount.
+///
+/// Only used to report balances that can go negative against a credit limit, it can be created
+/// from `Amount::signed_sub`.
+#[derive(Debug, PartialEq, PartialOrd, Clone, Copy, Serialize)]
+pub struct SignedAmount(Decimal);
+
 #[derive(Error, Debug)]
 #[error("negative amounts are not allowed")]
 pub struct NegativeAmountError;
 
+/// The ways arithmetic on an `Amount` can fail.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum AmountError {
+    /// The result is too big to be represented.
+    #[error("amount overflow")]
+    Overflow,
+    /// The result would be negative.
+    #[error("amount underflow")]
+    Underflow,
+}
+
+impl Display for Amount {
+    /// Format as a decimal.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Display for SignedAmount {
+    /// Format as a decimal.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 impl TryFrom<Decimal> for Amount {
     type Error = NegativeAmountError;
 
@@ -37,54 +95,70 @@ impl TryFrom<Decimal> for Amount {
     }
 }
 
-/// Add two amounts together
-impl Add for Amount {
-    type Output = Self;
-    fn add(self, rhs: Self) -> Self::Output {
-        Amount(self.0 + rhs.0)
+/// Expose ZERO as constructor.
+///
+/// We could have implemented `Default`, but using a constant ZERO is much more explicit about which is the
+/// default value.
+impl Amount {
+    pub const ZERO: Amount = Amount(Decimal::ZERO);
+    pub const MAX: Amount = Amount(Decimal::MAX);
+
+    /// Add two amounts together
+    ///
+    /// # Errors
+    ///
+    /// Returns `AmountError::Overflow` if the result can't be represented.
+    pub fn checked_add(self, rhs: Self) -> Result<Self, AmountError> {
+        self.0
+            .checked_add(rhs.0)
+            .map(Amount)
+            .ok_or(AmountError::Overflow)
     }
-}
 
-/// Subtract rhs from lhs
-///
-/// Clips at 0
-impl Sub for Amount {
-    type Output = Self;
-    fn sub(self, rhs: Self) -> Self::Output {
+    /// Subtract rhs from lhs
+    ///
+    /// # Errors
+    ///
+    /// Returns `AmountError::Underflow` if rhs is bigger than lhs, as amounts can't be negative.
+    pub fn checked_sub(self, rhs: Self) -> Result<Self, AmountError> {
         if self.0 < rhs.0 {
-            Amount::ZERO
+            Err(AmountError::Underflow)
         } else {
-            Amount(self.0 - rhs.0)
+            Ok(Amount(self.0 - rhs.0))
         }
     }
-}
 
-/// Add two amounts and store the result on lhs
-impl AddAssign for Amount {
-    fn add_assign(&mut self, rhs: Self) {
-        self.0 += rhs.0;
+    /// Subtract rhs from lhs, allowing for a negative result
+    #[must_use]
+    pub fn signed_sub(self, rhs: Self) -> SignedAmount {
+        // Subtracting zero from zero would drop the scale, e.g. `0.0000` would become `0`
+        if rhs.0.is_zero() {
+            return SignedAmount(self.0);
+        }
+        SignedAmount(self.0 - rhs.0)
     }
-}
 
-/// Sub rhs from lhs and store the result on lhs
-///
-/// Clips at 0
-impl SubAssign for Amount {
-    fn sub_assign(&mut self, rhs: Self) {
-        if self.0 < rhs.0 {
-            self.0 = Amount::ZERO.0;
-        } else {
-            self.0 -= rhs.0;
-        }
+    /// Whether this amount is an exact, non-zero, multiple of rhs
+    #[must_use]
+    pub fn is_multiple_of(self, rhs: Self) -> bool {
+        !self.0.is_zero() && self.0.checked_rem(rhs.0).is_some_and(|r| r.is_zero())
     }
-}
 
-/// Expose ZERO as constructor.
-///
-/// We could have implemented `Default`, but using a constant ZERO is much more explicit about which is the
-/// default value.
-impl Amount {
-    pub const ZERO: Amount = Amount(Decimal::ZERO);
+    /// Compute a percentage of this amount, rounded to 4 decimal places
+    ///
+    /// The percentage is scaled down first, so a fraction of a huge amount doesn't overflow.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AmountError::Overflow` if the result can't be represented.
+    pub fn checked_percent(self, percent: Self) -> Result<Self, AmountError> {
+        percent
+            .0
+            .checked_div(Decimal::ONE_HUNDRED)
+            .and_then(|rate| self.0.checked_mul(rate))
+            .map(|d| Amount(d.round_dp(4)))
+            .ok_or(AmountError::Overflow)
+    }
 }
 
 #[cfg(test)]
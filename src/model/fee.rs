@@ -0,0 +1,90 @@
+use serde::Deserialize;
+
+use super::{
+    common::{Amount, AmountError, Client},
+    transaction::Type,
+};
+
+/// A fee schedule
+///
+/// Configures the fee charged for each transaction type, none if not provided. Fees are debited
+/// from the client's available funds and posted to the `house` account.
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FeeSchedule {
+    /// The client whose account collects the fees
+    pub house: Client,
+    pub deposit: Option<Fee>,
+    pub withdrawal: Option<Fee>,
+    pub transfer: Option<Fee>,
+    pub dispute: Option<Fee>,
+    pub resolve: Option<Fee>,
+    pub chargeback: Option<Fee>,
+}
+
+/// A fee
+///
+/// A `flat` amount plus a `percent` of the transaction's amount, capped between `min` and `max`
+/// if provided. Every part is optional and zero by default.
+#[derive(Debug, PartialEq, Clone, Copy, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Fee {
+    /// A fixed amount charged on every transaction
+    pub flat: Amount,
+    /// A percentage of the transaction's amount, e.g. 1.5 for 1.5%
+    pub percent: Amount,
+    /// The minimum fee charged
+    pub min: Option<Amount>,
+    /// The maximum fee charged
+    pub max: Option<Amount>,
+}
+
+impl FeeSchedule {
+    /// Get the fee for a transaction type, if any
+    #[must_use]
+    pub fn fee_for(&self, t_type: &Type) -> Option<Fee> {
+        match t_type {
+            Type::Deposit(_) => self.deposit,
+            Type::Withdrawal(_) => self.withdrawal,
+            Type::Transfer(_) => self.transfer,
+            Type::Dispute(_) => self.dispute,
+            Type::Resolve(_) => self.resolve,
+            Type::Chargeback(_) => self.chargeback,
+        }
+    }
+}
+
+impl Default for Fee {
+    fn default() -> Self {
+        Self {
+            flat: Amount::ZERO,
+            percent: Amount::ZERO,
+            min: None,
+            max: None,
+        }
+    }
+}
+
+impl Fee {
+    /// Compute the fee for a transaction of `amount`
+    ///
+    /// # Errors
+    ///
+    /// Returns `AmountError::Overflow` if the fee can't be represented.
+    pub fn compute(&self, amount: Amount) -> Result<Amount, AmountError> {
+        let mut fee = self
+            .flat
+            .checked_add(amount.checked_percent(self.percent)?)?;
+        if let Some(min) = self.min
+            && fee < min
+        {
+            fee = min;
+        }
+        if let Some(max) = self.max
+            && fee > max
+        {
+            fee = max;
+        }
+        Ok(fee)
+    }
+}
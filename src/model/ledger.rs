@@ -1,17 +1,148 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    str::FromStr,
+};
 
-use anyhow::Result;
+use anyhow::{Context, Result, anyhow, bail};
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-use super::{account::Account, common::Client, transaction::Transaction};
+use super::{
+    account::Account,
+    alert::{Alert, AlertRule, Severity},
+    balance::{Balance, BalanceError},
+    common::{Amount, Client, Timestamp, Tx},
+    fee::{Fee, FeeSchedule},
+    observer::{BalanceChange, LedgerObserver},
+    risk::RiskConfig,
+    transaction::{Deposit, Transaction, Transfer, Type},
+    validator::{Flag, RejectedError, TransactionValidator, Verdict},
+};
 
 /// A ledger
 ///
 /// A ledger represents the status of a set of accounts after applying a set of transactions to
 /// them.
-#[derive(Debug, Default)]
+///
+/// Only the state of a ledger is serialized, e.g. to checkpoint it, see `restore`. Its
+/// configuration, validators, observers and undrained events are skipped.
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Ledger {
     /// The set of accounts (as a `HashMap` for fast random access using the `Client`)
     pub accounts: HashMap<Client, Account>,
+    /// The set of clients whose accounts have already been finalized and removed from `accounts`
+    pub finalized: HashSet<Client>,
+    /// How long deposits are retained to be disputed, forever if `None`
+    #[serde(skip)]
+    pub dispute_window: Option<DisputeWindow>,
+    /// When and how opened disputes are automatically closed, never if `None`
+    #[serde(skip)]
+    pub dispute_expiry: Option<DisputeExpiry>,
+    /// Whether the amounts of disputes, resolves and chargebacks are honored
+    ///
+    /// Disabled by default, in which case any amount is ignored and they always apply to the whole
+    /// deposit.
+    #[serde(skip)]
+    pub partial_disputes: bool,
+    /// The fees charged for each transaction type, none if `None`
+    #[serde(skip)]
+    pub fee_schedule: Option<FeeSchedule>,
+    /// The credit limit of each client, the rest of the accounts can't be overdrawn
+    ///
+    /// Limits are set on the accounts as they are created.
+    #[serde(skip)]
+    pub credit_limits: HashMap<Client, Amount>,
+    /// The risk limits on the outflows of each client, set on the accounts as they are created
+    #[serde(skip)]
+    pub risk_limits: RiskConfig,
+    /// The custom checks consulted, in order, before and after applying each transaction
+    #[serde(skip)]
+    pub validators: Vec<Box<dyn TransactionValidator>>,
+    /// The observers notified, in order, of the state changes of this ledger
+    #[serde(skip)]
+    pub observers: Vec<Box<dyn LedgerObserver>>,
+    /// The transactions applied by the ledger on its own, e.g. to close expired disputes
+    ///
+    /// They are recorded in the order they are applied, and it's up to the consumer to drain them.
+    #[serde(skip)]
+    pub synthetic: Vec<Transaction>,
+    /// The transactions flagged by the validators
+    ///
+    /// They are recorded in the order they are flagged, and it's up to the consumer to drain them.
+    /// Flags of transactions that end up rejected are dropped.
+    #[serde(skip)]
+    pub flags: Vec<Flag>,
+    /// The suspicious events raised while applying transactions
+    ///
+    /// They are recorded in the order they are raised, and it's up to the consumer to drain them.
+    #[serde(skip)]
+    pub alerts: Vec<Alert>,
+    /// How many withdrawals or transfers in a row can be rejected for a client before raising an
+    /// alert, never if `None`
+    #[serde(skip)]
+    pub rejected_withdrawals_alert: Option<u32>,
+    /// The number of transactions applied to this ledger so far
+    seq: u64,
+    /// The deposits retained under the dispute window, in the order they were applied, along with
+    /// the sequence number and timestamp they are retained from
+    retained: VecDeque<(u64, Option<Timestamp>, Client, Tx)>,
+    /// The number of withdrawals and transfers rejected in a row for each client
+    rejected_withdrawals: HashMap<Client, u32>,
+}
+
+/// How long a deposit is retained to be disputed.
+///
+/// Once the window is over the deposit is evicted from its account, and any dispute against it is
+/// rejected with a `DisputeWindowExpiredError`. Deposits with an opened dispute are never evicted
+/// until the dispute is closed.
+///
+/// Deposits are evicted in the order they were applied, and transaction ids are assumed to increase
+/// for each client, see `Account::evict`.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum DisputeWindow {
+    /// The deposit is retained for this many ledger transactions after its own.
+    Transactions(u64),
+    /// The deposit is retained until a transaction is timestamped this long after it, or more.
+    ///
+    /// Deposits without a timestamp are retained forever.
+    Duration(TimeDelta),
+}
+
+/// When and how opened disputes are automatically closed.
+///
+/// A timestamped dispute expires once a transaction for the same client is timestamped `after`
+/// or more past the dispute. Disputes without a timestamp never expire.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub struct DisputeExpiry {
+    /// How long a dispute can stay opened
+    pub after: TimeDelta,
+    /// How the dispute is closed once expired
+    pub policy: ExpiryPolicy,
+}
+
+/// How an expired dispute is closed.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExpiryPolicy {
+    /// The held funds are released, as if a resolve had been received
+    #[default]
+    Resolve,
+    /// The held funds are reverted, as if a chargeback had been received
+    Chargeback,
+}
+
+impl FromStr for ExpiryPolicy {
+    type Err = anyhow::Error;
+
+    /// Parse from `resolve` or `chargeback`.
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "resolve" => Ok(ExpiryPolicy::Resolve),
+            "chargeback" => Ok(ExpiryPolicy::Chargeback),
+            _ => bail!("unknown expiry policy {s}, expected resolve or chargeback"),
+        }
+    }
 }
 
 impl Ledger {
@@ -21,9 +152,77 @@ impl Ledger {
     /// correct account.
     ///
     /// # Errors
-    /// Returns `anyhow::Error` if the transaction fails to be processed.
+    /// Returns `anyhow::Error` if the transaction fails to be processed, or if the account it
+    /// belongs to has already been finalized. Returns a `RejectedError` if a validator rejects it.
     pub fn apply(&mut self, txn: Transaction) -> Result<()> {
-        self.get_account_for(txn.client).apply(txn)
+        // Expired disputes are closed by synthetic transactions of their own, ahead of this one
+        self.expire_disputes_of(&txn);
+        let locked = self.accounts.get(&txn.client).is_some_and(|a| a.locked);
+        let before = self.observed_balances(&txn);
+        let result = self.try_apply(txn);
+        self.raise_alerts(&txn, &result, locked);
+        self.notify(&txn, &result, locked, before);
+        result
+    }
+
+    fn try_apply(&mut self, txn: Transaction) -> Result<()> {
+        let txn = if self.partial_disputes {
+            txn
+        } else {
+            txn.without_dispute_amount()
+        };
+        self.seq += 1;
+        self.evict_expired_deposits(txn.timestamp);
+
+        if self.finalized.contains(&txn.client) {
+            bail!("account {} is already finalized", txn.client.0)
+        }
+        let fee = self.fee_for(&txn);
+        let account = self
+            .accounts
+            .entry(txn.client)
+            .or_insert_with(|| open_account(&self.credit_limits, &self.risk_limits, txn.client));
+        // Flags raised before applying are only kept if the transaction goes through
+        let mut flags = Vec::new();
+        for validator in &mut self.validators {
+            match validator.before(account, &txn) {
+                Verdict::Accept => {}
+                Verdict::Reject(reason) => return Err(RejectedError { tx: txn.tx, reason }.into()),
+                Verdict::Flag(reason) => flags.push(Flag {
+                    client: txn.client,
+                    tx: txn.tx,
+                    reason,
+                }),
+            }
+        }
+        let upfront_fee = Ledger::upfront_fee(fee, account, &txn)?;
+        if let Type::Transfer(transfer) = txn.t_type {
+            self.transfer(txn, transfer)?;
+        } else {
+            account.apply(txn)?;
+        }
+        self.flags.append(&mut flags);
+        self.charge_fee(&txn, upfront_fee);
+        if let Some(account) = self.accounts.get(&txn.client) {
+            for validator in &mut self.validators {
+                if let Some(reason) = validator.after(account, &txn) {
+                    self.flags.push(Flag {
+                        client: txn.client,
+                        tx: txn.tx,
+                        reason,
+                    });
+                }
+            }
+        }
+
+        if let Type::Deposit(_) = txn.t_type
+            && let Some(window) = self.dispute_window
+            && (txn.timestamp.is_some() || matches!(window, DisputeWindow::Transactions(_)))
+        {
+            self.retained
+                .push_back((self.seq, txn.timestamp, txn.client, txn.tx));
+        }
+        Ok(())
     }
 
     /// Get the account for a client
@@ -32,6 +231,466 @@ impl Ledger {
     pub fn get_account_for(&mut self, client: Client) -> &mut Account {
         self.accounts
             .entry(client)
-            .or_insert_with(|| Account::new(client))
+            .or_insert_with(|| open_account(&self.credit_limits, &self.risk_limits, client))
+    }
+
+    /// Expire disputes on all accounts
+    ///
+    /// Closes every dispute expired at `now` regardless of the client, e.g. at the end of the
+    /// input. The synthetic transactions applied are recorded in `synthetic`.
+    pub fn expire_disputes(&mut self, now: Timestamp) {
+        let Some(expiry) = self.dispute_expiry else {
+            return;
+        };
+        for account in self.accounts.values_mut() {
+            expire_disputes(
+                account,
+                expiry,
+                now,
+                &mut self.synthetic,
+                &mut self.alerts,
+                &mut self.observers,
+            );
+        }
+    }
+
+    /// Get the timestamp of the latest timestamped transaction applied to any account, if any
+    ///
+    /// It's the closest to a current time the ledger has, e.g. to expire disputes at the end of the
+    /// input.
+    #[must_use]
+    pub fn latest_timestamp(&self) -> Option<Timestamp> {
+        self.accounts
+            .values()
+            .filter_map(|account| account.last_timestamp)
+            .max()
+    }
+
+    /// Expire the disputes of a transaction's client at its timestamp, if any
+    fn expire_disputes_of(&mut self, txn: &Transaction) {
+        if let (Some(expiry), Some(now), Some(account)) = (
+            self.dispute_expiry,
+            txn.timestamp,
+            self.accounts.get_mut(&txn.client),
+        ) {
+            expire_disputes(
+                account,
+                expiry,
+                now,
+                &mut self.synthetic,
+                &mut self.alerts,
+                &mut self.observers,
+            );
+        }
+    }
+
+    /// Finalize the account for a client
+    ///
+    /// Removes the account from the ledger and hands it over to the caller, so it can be output
+    /// without waiting for the rest of the transactions. Any further transaction for this client
+    /// will be rejected by `apply`.
+    ///
+    /// Returns `None` if the client has no account on this ledger, or if it's the house account
+    /// of the fee schedule, which keeps collecting fees until the end.
+    pub fn finalize(&mut self, client: Client) -> Option<Account> {
+        if self.fee_schedule.is_some_and(|fees| fees.house == client) {
+            return None;
+        }
+        self.finalized.insert(client);
+        self.accounts.remove(&client)
+    }
+
+    /// Restore the state of a checkpointed ledger
+    ///
+    /// Replaces the accounts of this ledger, and the bookkeeping of its dispute window and alerts,
+    /// with the ones of `checkpoint`. The configuration of this ledger is kept as is, so it should
+    /// be the same the checkpointed ledger had.
+    pub fn restore(&mut self, checkpoint: Ledger) {
+        self.accounts = checkpoint.accounts;
+        self.finalized = checkpoint.finalized;
+        self.seq = checkpoint.seq;
+        self.retained = checkpoint.retained;
+        self.rejected_withdrawals = checkpoint.rejected_withdrawals;
+    }
+
+    /// Save the state of each validator, in order, to checkpoint it along with the ledger
+    #[must_use]
+    pub fn validator_states(&self) -> Vec<Value> {
+        self.validators.iter().map(|v| v.save()).collect()
+    }
+
+    /// Restore the state of each validator, in order, from `validator_states`
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the validators differ from the ones the states were saved from.
+    pub fn restore_validators(&mut self, states: Vec<Value>) -> Result<()> {
+        if states.len() != self.validators.len() {
+            bail!(
+                "{} validators were checkpointed, but {} are configured",
+                states.len(),
+                self.validators.len()
+            );
+        }
+        for (validator, state) in self.validators.iter_mut().zip(states) {
+            validator
+                .restore(state)
+                .with_context(|| format!("invalid state for {validator:?}"))?;
+        }
+        Ok(())
+    }
+
+    /// Apply a transfer
+    ///
+    /// Debits the sender and credits the recipient atomically: the recipient is checked first, so
+    /// either both sides are applied or none is. The recipient's account is only created if the
+    /// transfer succeeds.
+    fn transfer(&mut self, txn: Transaction, transfer: Transfer) -> Result<()> {
+        if transfer.to == txn.client {
+            bail!("can't transfer to the same client for tx {:?}", txn.tx)
+        }
+        if self.finalized.contains(&transfer.to) {
+            bail!("account {} is already finalized", transfer.to.0)
+        }
+
+        let existed = self.accounts.contains_key(&transfer.to);
+        let mut recipient = self
+            .accounts
+            .remove(&transfer.to)
+            .unwrap_or_else(|| open_account(&self.credit_limits, &self.risk_limits, transfer.to));
+
+        let result = recipient
+            .check_incoming(transfer.amount)
+            .and_then(|credited| {
+                self.get_account_for(txn.client).apply(txn)?;
+                recipient.balance = credited;
+                Ok(())
+            });
+
+        if existed || result.is_ok() {
+            self.accounts.insert(transfer.to, recipient);
+        }
+        result
+    }
+
+    /// Raise the alerts for the `result` of applying a transaction
+    ///
+    /// `locked` is whether the account was locked before applying it.
+    fn raise_alerts(&mut self, txn: &Transaction, result: &Result<()>, locked: bool) {
+        let mut raise = |rule, severity| {
+            self.alerts.push(Alert {
+                client: txn.client,
+                tx: txn.tx,
+                rule,
+                severity,
+            });
+        };
+
+        match (txn.t_type, result) {
+            (Type::Dispute(_), Err(e))
+                if e.downcast_ref::<BalanceError>() == Some(&BalanceError::InsufficientFunds) =>
+            {
+                raise(AlertRule::DisputeInsufficientFunds, Severity::Warning);
+            }
+            (Type::Withdrawal(_) | Type::Transfer(_), Err(_)) => {
+                let rejected = self.rejected_withdrawals.entry(txn.client).or_default();
+                *rejected += 1;
+                if Some(*rejected) == self.rejected_withdrawals_alert {
+                    raise(AlertRule::RepeatedRejectedWithdrawals, Severity::Warning);
+                }
+            }
+            (Type::Withdrawal(_) | Type::Transfer(_), Ok(())) => {
+                self.rejected_withdrawals.remove(&txn.client);
+            }
+            _ => {}
+        }
+        if !locked && self.accounts.get(&txn.client).is_some_and(|a| a.locked) {
+            raise(AlertRule::AccountLocked, Severity::Critical);
+        }
+    }
+
+    /// Get the balances the observers are notified about for a transaction, before applying it
+    ///
+    /// Those are the balances of its client, then the recipient of a transfer and the house account
+    /// of a fee, if any. Nothing is observed if there are no observers.
+    fn observed_balances(&self, txn: &Transaction) -> Vec<(Client, Balance)> {
+        if self.observers.is_empty() {
+            return Vec::new();
+        }
+        let mut clients = vec![txn.client];
+        if let Type::Transfer(transfer) = txn.t_type {
+            clients.push(transfer.to);
+        }
+        if let Some(fees) = self.fee_schedule
+            && self.fee_for(txn).is_some()
+            && !clients.contains(&fees.house)
+        {
+            clients.push(fees.house);
+        }
+        clients
+            .into_iter()
+            .map(|client| (client, self.balance_of(client)))
+            .collect()
+    }
+
+    /// Notify the observers of the `result` of applying a transaction
+    ///
+    /// `locked` is whether the account was locked, and `before` the observed balances, before
+    /// applying it.
+    fn notify(
+        &mut self,
+        txn: &Transaction,
+        result: &Result<()>,
+        locked: bool,
+        before: Vec<(Client, Balance)>,
+    ) {
+        if self.observers.is_empty() {
+            return;
+        }
+        let changes: Vec<BalanceChange> = before
+            .into_iter()
+            .map(|(client, before)| BalanceChange {
+                client,
+                before,
+                after: self.balance_of(client),
+            })
+            .collect();
+        let change = &changes[0];
+        let account = self.accounts.get(&txn.client);
+
+        for observer in &mut self.observers {
+            match result {
+                Ok(()) => observer.applied(txn, &changes),
+                Err(e) => observer.rejected(txn, e),
+            }
+        }
+        if let (Ok(()), Type::Dispute(_) | Type::Resolve(_) | Type::Chargeback(_)) =
+            (result, txn.t_type)
+            && let Some(event) = account
+                .and_then(|a| a.dispute_history.get(&txn.tx))
+                .and_then(|history| history.last())
+        {
+            for observer in &mut self.observers {
+                observer.dispute_transition(txn, event, change);
+            }
+        }
+        if !locked && account.is_some_and(|a| a.locked) {
+            for observer in &mut self.observers {
+                observer.locked(txn, change);
+            }
+        }
+    }
+
+    /// Get the balance of a client, a default one if it has no account
+    fn balance_of(&self, client: Client) -> Balance {
+        self.accounts
+            .get(&client)
+            .map_or(Balance::default(), |account| account.balance)
+    }
+
+    /// Compute the fee of a withdrawal or a transfer before applying it
+    ///
+    /// These are charged on top of the amount debited, so they are rejected unless the available
+    /// funds, and credit limit, cover both. Other transactions are charged once applied, see
+    /// `charge_fee`.
+    fn upfront_fee(
+        fee: Option<Fee>,
+        account: &Account,
+        txn: &Transaction,
+    ) -> Result<Option<Amount>> {
+        let amount = match txn.t_type {
+            Type::Withdrawal(withdrawal) => withdrawal.amount,
+            Type::Transfer(transfer) => transfer.amount,
+            _ => return Ok(None),
+        };
+        let Some(fee) = fee else {
+            return Ok(None);
+        };
+        let fee = fee
+            .compute(amount)
+            .map_err(|e| anyhow!("{e} computing the fee for tx {:?}", txn.tx))?;
+
+        let mut balance = account.balance;
+        if amount.checked_add(fee).is_ok_and(|debit| {
+            balance
+                .debit_with_limit(debit, account.credit_limit)
+                .is_ok()
+        }) {
+            Ok(Some(fee))
+        } else {
+            bail!(
+                "{} to cover the fee for tx {:?}",
+                BalanceError::InsufficientFunds,
+                txn.tx
+            )
+        }
+    }
+
+    /// Charge the fee of an applied transaction, if any, and post it to the house account
+    ///
+    /// Uses the `upfront` fee if already computed. Otherwise the fee is computed on the amount
+    /// deposited, disputed, resolved or charged back, and capped at the available funds so it
+    /// never makes a balance negative. The part that can't be covered is waived.
+    ///
+    /// The whole fee is waived if the house account is locked or already finalized, raising a
+    /// `FeeWaived` alert.
+    fn charge_fee(&mut self, txn: &Transaction, upfront: Option<Amount>) {
+        let (Some(fees), Some(fee)) = (self.fee_schedule, self.fee_for(txn)) else {
+            return;
+        };
+        let house = self.accounts.get(&fees.house);
+        let closed =
+            self.finalized.contains(&fees.house) || house.is_some_and(|house| house.locked);
+        let mut house = house.map_or(Balance::default(), |house| house.balance);
+        let Some(account) = self.accounts.get_mut(&txn.client) else {
+            return;
+        };
+        let fee = if let Some(fee) = upfront {
+            fee
+        } else {
+            let amount = match txn.t_type {
+                Type::Deposit(deposit) => Some(deposit.amount),
+                _ => account
+                    .dispute_history
+                    .get(&txn.tx)
+                    .and_then(|history| history.last())
+                    .map(|event| event.amount),
+            };
+            let available = account.balance.available();
+            // A fee that can't be computed is waived, rather than guessed
+            match amount.map(|amount| fee.compute(amount)) {
+                Some(Ok(fee)) if fee < available => fee,
+                Some(Ok(_)) => available,
+                Some(Err(_)) | None => return,
+            }
+        };
+        if fee == Amount::ZERO {
+            return;
+        }
+        if closed {
+            self.alerts.push(Alert {
+                client: txn.client,
+                tx: txn.tx,
+                rule: AlertRule::FeeWaived,
+                severity: Severity::Warning,
+            });
+            return;
+        }
+
+        // Check both sides before committing, so the fee is either fully posted or not at all
+        let mut balance = account.balance;
+        let (Ok(()), Ok(()), Ok(paid)) = (
+            balance.debit_with_limit(fee, account.credit_limit),
+            house.credit(fee),
+            account.fees.checked_add(fee),
+        ) else {
+            return;
+        };
+        account.balance = balance;
+        account.fees = paid;
+        self.get_account_for(fees.house).balance = house;
+    }
+
+    /// Get the fee of a transaction, unless it's charged to the house account itself
+    fn fee_for(&self, txn: &Transaction) -> Option<Fee> {
+        let fees = self.fee_schedule?;
+        if fees.house == txn.client {
+            return None;
+        }
+        fees.fee_for(&txn.t_type)
+    }
+
+    /// Evict the deposits whose dispute window is over
+    ///
+    /// `now` is the timestamp of the transaction being applied, if any. Deposits that are currently
+    /// disputed are retained for another window instead.
+    fn evict_expired_deposits(&mut self, now: Option<Timestamp>) {
+        let Some(window) = self.dispute_window else {
+            return;
+        };
+
+        while let Some(&(seq, timestamp, client, tx)) = self.retained.front() {
+            let expired = match window {
+                DisputeWindow::Transactions(window) => seq.saturating_add(window) < self.seq,
+                DisputeWindow::Duration(window) => timestamp
+                    .and_then(|t| t.0.checked_add_signed(window))
+                    .zip(now)
+                    .is_some_and(|(deadline, now)| deadline <= now.0),
+            };
+            if !expired {
+                break;
+            }
+            self.retained.pop_front();
+
+            let Some(account) = self.accounts.get_mut(&client) else {
+                continue;
+            };
+            if account.deposits.get(&tx).is_some_and(Deposit::is_disputed) {
+                self.retained.push_back((self.seq, now, client, tx));
+            } else {
+                account.evict(tx);
+            }
+        }
+    }
+}
+
+/// Open a new account for a client, with its credit and risk limits if any
+fn open_account(
+    credit_limits: &HashMap<Client, Amount>,
+    risk_limits: &RiskConfig,
+    client: Client,
+) -> Account {
+    let mut account = Account::new(client);
+    if let Some(limit) = credit_limits.get(&client) {
+        account.credit_limit = *limit;
+    }
+    account.risk_limits = risk_limits.limits_for(client);
+    account
+}
+
+/// Close the expired disputes of an account, notifying the observers
+///
+/// The synthetic transactions applied are recorded in `synthetic`, and an account they lock is
+/// alerted on in `alerts`.
+fn expire_disputes(
+    account: &mut Account,
+    expiry: DisputeExpiry,
+    now: Timestamp,
+    synthetic: &mut Vec<Transaction>,
+    alerts: &mut Vec<Alert>,
+    observers: &mut [Box<dyn LedgerObserver>],
+) {
+    for txn in account.expired_disputes(expiry, now) {
+        let (before, locked) = (account.balance, account.locked);
+        if account.apply(txn).is_err() {
+            continue;
+        }
+        synthetic.push(txn);
+
+        let change = BalanceChange {
+            client: account.client,
+            before,
+            after: account.balance,
+        };
+        if let Some(event) = account
+            .dispute_history
+            .get(&txn.tx)
+            .and_then(|history| history.last())
+        {
+            for observer in observers.iter_mut() {
+                observer.dispute_transition(&txn, event, &change);
+            }
+        }
+        if !locked && account.locked {
+            alerts.push(Alert {
+                client: account.client,
+                tx: txn.tx,
+                rule: AlertRule::AccountLocked,
+                severity: Severity::Critical,
+            });
+            for observer in observers.iter_mut() {
+                observer.locked(&txn, &change);
+            }
+        }
     }
 }
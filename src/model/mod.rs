@@ -1,8 +1,14 @@
 pub mod account;
+pub mod alert;
 pub mod balance;
 pub mod common;
+pub mod fee;
 pub mod ledger;
+pub mod observer;
+pub mod period;
+pub mod risk;
 pub mod transaction;
+pub mod validator;
 
 #[cfg(test)]
 pub mod test;
@@ -0,0 +1,48 @@
+use std::fmt::Debug;
+
+use super::{
+    balance::Balance,
+    common::Client,
+    transaction::{DisputeEvent, Transaction},
+};
+
+/// An observer of the state changes of a `Ledger`
+///
+/// Observers are registered on `Ledger::observers`, and notified in order once a transaction has
+/// been applied or rejected. Every callback does nothing by default, so an observer only needs to
+/// implement the ones it cares about.
+pub trait LedgerObserver: Debug + Send {
+    /// A transaction was applied
+    ///
+    /// `changes` holds the balance of every account the transaction could affect, i.e. its
+    /// client's first, then the recipient of a transfer and the house account of a fee, if any.
+    fn applied(&mut self, _txn: &Transaction, _changes: &[BalanceChange]) {}
+
+    /// A transaction was rejected with `error`
+    fn rejected(&mut self, _txn: &Transaction, _error: &anyhow::Error) {}
+
+    /// A deposit went through a dispute lifecycle transition
+    ///
+    /// Also notified for the disputes closed by the ledger on its own once expired, in which case
+    /// `txn` is the synthetic resolve or chargeback.
+    fn dispute_transition(
+        &mut self,
+        _txn: &Transaction,
+        _event: &DisputeEvent,
+        _change: &BalanceChange,
+    ) {
+    }
+
+    /// An account was locked by a transaction, even a rejected one
+    fn locked(&mut self, _txn: &Transaction, _change: &BalanceChange) {}
+}
+
+/// The balance of an account before and after a transaction
+///
+/// Accounts that don't exist, yet or anymore, have a default balance.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct BalanceChange {
+    pub client: Client,
+    pub before: Balance,
+    pub after: Balance,
+}
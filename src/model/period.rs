@@ -0,0 +1,69 @@
+use std::collections::HashMap;
+
+use anyhow::Result;
+
+use super::{
+    account::Account,
+    balance::Balance,
+    common::{Client, Timestamp},
+    ledger::Ledger,
+    transaction::Transaction,
+};
+
+/// A period report
+///
+/// Tracks the balances of a ledger's accounts over the period that starts at `since`, inclusive.
+/// Transactions are applied through the report, so it can record the opening balance of each
+/// account right before its first transaction within the period.
+///
+/// Transactions are only assumed to be ordered in time per client, so the opening balances are
+/// captured per account rather than at a single point of the input.
+#[derive(Debug)]
+pub struct PeriodReport {
+    /// The start of the period
+    pub since: Timestamp,
+    /// The balance of each account active within the period, as it was at `since`
+    pub opening: HashMap<Client, Balance>,
+}
+
+impl PeriodReport {
+    /// Start a period report
+    #[must_use]
+    pub fn new(since: Timestamp) -> Self {
+        Self {
+            since,
+            opening: HashMap::new(),
+        }
+    }
+
+    /// Apply a transaction
+    ///
+    /// Records the opening balance of the account if this is its first transaction at or after
+    /// `since`, and then applies the transaction to the `ledger`.
+    ///
+    /// # Errors
+    /// Returns `anyhow::Error` if the transaction fails to be processed by the ledger.
+    pub fn apply(&mut self, ledger: &mut Ledger, txn: Transaction) -> Result<()> {
+        if txn.timestamp.is_some_and(|t| t >= self.since) && !self.opening.contains_key(&txn.client)
+        {
+            let balance = ledger
+                .accounts
+                .get(&txn.client)
+                .map(|acc| acc.balance)
+                .unwrap_or_default();
+            self.opening.insert(txn.client, balance);
+        }
+        ledger.apply(txn)
+    }
+
+    /// The opening balance of an account
+    ///
+    /// Accounts without activity within the period open with their current balance.
+    #[must_use]
+    pub fn opening(&self, account: &Account) -> Balance {
+        self.opening
+            .get(&account.client)
+            .copied()
+            .unwrap_or(account.balance)
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::{HashMap, VecDeque};
+
+use chrono::{NaiveDate, TimeDelta};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use super::common::{Amount, Client, Timestamp, Tx};
+
+/// Risk limits configuration
+///
+/// The `default` limits apply to every client without its own entry in `clients`. A client's
+/// own limits replace the default ones as a whole, they are not merged.
+#[derive(Debug, PartialEq, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct RiskConfig {
+    pub default: Option<RiskLimits>,
+    #[serde(default)]
+    pub clients: HashMap<Client, RiskLimits>,
+}
+
+/// Risk limits on the outflows of an account, i.e. its withdrawals and transfers
+///
+/// Limits on a number of outflows in a window, or on the outflow of a day, can only be checked
+/// for timestamped transactions. Undated transactions are only checked against `max_withdrawal`.
+#[derive(Debug, PartialEq, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct RiskLimits {
+    /// The maximum amount of a single outflow
+    pub max_withdrawal: Option<Amount>,
+    /// The maximum number of outflows in a rolling window
+    pub max_withdrawals: Option<Velocity>,
+    /// The maximum amount of all outflows in a calendar day, in UTC
+    pub max_daily_outflow: Option<Amount>,
+    /// Whether the account is locked when a limit is breached
+    pub lock_on_breach: bool,
+}
+
+/// A maximum number of outflows in a rolling window
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Velocity {
+    /// The maximum number of outflows
+    pub count: usize,
+    /// The window length, e.g. `1h`
+    #[serde(
+        serialize_with = "serialize_duration",
+        deserialize_with = "deserialize_duration"
+    )]
+    pub window: TimeDelta,
+}
+
+/// The outflows of an account the risk limits are checked against
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
+pub struct Outflows {
+    /// The instants of the recent timestamped outflows, oldest first
+    pub recent: VecDeque<Timestamp>,
+    /// The day of the latest timestamped outflow, along with the total outflow of that day
+    pub daily: Option<(NaiveDate, Amount)>,
+}
+
+/// An outflow was rejected for breaching a risk limit.
+#[derive(Error, Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RiskLimitError {
+    #[error("{0:?} exceeds the maximum withdrawal amount")]
+    MaxWithdrawal(Tx),
+    #[error("{0:?} exceeds the maximum number of withdrawals in the window")]
+    MaxWithdrawals(Tx),
+    #[error("{0:?} exceeds the maximum daily outflow")]
+    MaxDailyOutflow(Tx),
+}
+
+impl RiskConfig {
+    /// Get the risk limits of a client, if any
+    #[must_use]
+    pub fn limits_for(&self, client: Client) -> Option<RiskLimits> {
+        self.clients.get(&client).copied().or(self.default)
+    }
+}
+
+impl RiskLimits {
+    /// Check an outflow of `amount` against these limits
+    ///
+    /// # Errors
+    ///
+    /// Returns the `RiskLimitError` of the first limit breached.
+    pub fn check(
+        &self,
+        outflows: &Outflows,
+        tx: Tx,
+        amount: Amount,
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), RiskLimitError> {
+        if self.max_withdrawal.is_some_and(|max| amount > max) {
+            return Err(RiskLimitError::MaxWithdrawal(tx));
+        }
+        let Some(now) = timestamp else {
+            return Ok(());
+        };
+        if let Some(velocity) = self.max_withdrawals {
+            let recent = outflows
+                .recent
+                .iter()
+                .filter(|t| now.0.signed_duration_since(t.0) < velocity.window)
+                .count();
+            if recent >= velocity.count {
+                return Err(RiskLimitError::MaxWithdrawals(tx));
+            }
+        }
+        if let Some(max) = self.max_daily_outflow {
+            let today = match outflows.daily {
+                Some((day, outflow)) if day == now.0.date_naive() => outflow,
+                _ => Amount::ZERO,
+            };
+            if !today.checked_add(amount).is_ok_and(|total| total <= max) {
+                return Err(RiskLimitError::MaxDailyOutflow(tx));
+            }
+        }
+        Ok(())
+    }
+
+    /// Record an outflow of `amount` that was applied
+    ///
+    /// Outflows older than the velocity window are dropped, so they don't take up memory.
+    pub fn record(&self, outflows: &mut Outflows, amount: Amount, timestamp: Option<Timestamp>) {
+        let Some(now) = timestamp else {
+            return;
+        };
+        if let Some(velocity) = self.max_withdrawals {
+            while outflows
+                .recent
+                .front()
+                .is_some_and(|t| now.0.signed_duration_since(t.0) >= velocity.window)
+            {
+                outflows.recent.pop_front();
+            }
+            outflows.recent.push_back(now);
+        }
+        let today = now.0.date_naive();
+        let outflow = match outflows.daily {
+            Some((day, outflow)) if day == today => outflow,
+            _ => Amount::ZERO,
+        };
+        outflows.daily = Some((today, outflow.checked_add(amount).unwrap_or(Amount::MAX)));
+    }
+}
+
+/// Deserialize a human readable duration, e.g. 1h
+fn deserialize_duration<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TimeDelta, D::Error> {
+    let s = String::deserialize(deserializer)?;
+    let duration = humantime::parse_duration(&s).map_err(serde::de::Error::custom)?;
+    TimeDelta::from_std(duration).map_err(serde::de::Error::custom)
+}
+
+/// Serialize a duration in a human readable form, e.g. 1h
+fn serialize_duration<S: Serializer>(
+    duration: &TimeDelta,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    let duration = duration.to_std().map_err(serde::ser::Error::custom)?;
+    serializer.serialize_str(&humantime::format_duration(duration).to_string())
+}
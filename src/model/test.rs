@@ -1,146 +1,1759 @@
+use std::sync::{Arc, Mutex};
+
+use chrono::TimeDelta;
+use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
 
-use super::{account::Account, balance::Balance};
+use super::{
+    account::{Account, DisputeWindowExpiredError, NonMonotonicTimestampError},
+    balance::{Balance, BalanceError},
+    common::{AmountError, NegativeAmountError},
+};
+
+use super::{
+    alert::{Alert, AlertRule, Severity},
+    common::{Amount, Client, Timestamp, Tx},
+    fee::{Fee, FeeSchedule},
+    ledger::{DisputeExpiry, DisputeWindow, ExpiryPolicy, Ledger},
+    observer::{BalanceChange, LedgerObserver},
+    period::PeriodReport,
+    risk::{RiskConfig, RiskLimitError, RiskLimits, Velocity},
+    transaction::{Deposit, DisputeEvent, DisputeStatus, Transaction, Transfer, Type, Withdrawal},
+    validator::{
+        Flag, NewAccountOutflows, RejectedError, RoundDeposits, TransactionValidator, Verdict,
+    },
+};
+
+// Amount tests
+#[test]
+fn negative_amount_allow_only_nonnegative_values() {
+    Amount::try_from(dec!(-1)).expect_err("only non-negative values allowed");
+}
+
+#[test]
+fn positive_amount_checked_arithmetic() {
+    assert_eq!(Amount::TEN.checked_sub(Amount::TWO), Ok(amount(dec!(8))));
+    assert_eq!(Amount::TEN.checked_add(Amount::TWO), Ok(amount(dec!(12))));
+    assert_eq!(Amount::TEN.checked_sub(Amount::TEN), Ok(Amount::ZERO));
+}
+
+#[test]
+fn negative_amount_checked_sub_does_not_clip() {
+    assert_eq!(
+        Amount::ONE.checked_sub(Amount::TWO),
+        Err(AmountError::Underflow)
+    );
+}
+
+#[test]
+fn negative_amount_checked_add_does_not_panic_on_overflow() {
+    assert_eq!(
+        Amount::MAX.checked_add(Amount::ONE),
+        Err(AmountError::Overflow)
+    );
+}
+
+// Balance tests
+#[test]
+fn positive_balance_credit() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    assert_eq!(balance.available(), Amount::TEN);
+    assert_eq!(balance.held(), Amount::ZERO);
+}
+
+#[test]
+fn positive_balance_debit() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    balance
+        .debit(Amount::TWO)
+        .expect("there are sufficient available funds");
+    assert_eq!(balance.available(), amount(dec!(8)));
+    assert_eq!(balance.held(), Amount::ZERO);
+}
+
+#[test]
+fn positive_balance_hold() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    balance
+        .hold(Amount::TWO)
+        .expect("there are available sufficient funds");
+    assert_eq!(balance.available(), amount(dec!(8)));
+    assert_eq!(balance.held(), Amount::TWO);
+}
+
+#[test]
+fn positive_balance_release() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    balance
+        .hold(Amount::TWO)
+        .expect("there are sufficient available funds");
+    balance
+        .release(Amount::ONE)
+        .expect("there are sufficient funds on hold");
+    assert_eq!(balance.available(), amount(dec!(9)));
+    assert_eq!(balance.held(), Amount::ONE);
+}
+
+#[test]
+fn positive_balance_reimburse() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    balance
+        .hold(Amount::TWO)
+        .expect("there are sufficient available funds");
+    balance
+        .reimburse(Amount::ONE)
+        .expect("there are sufficient funds on hold");
+    assert_eq!(balance.available(), amount(dec!(8)));
+    assert_eq!(balance.held(), Amount::ONE);
+}
+
+// Negative balance tests
+
+#[test]
+fn negative_balance_debit_insufficient_available_funds() {
+    let mut balance = Balance::default();
+    balance
+        .debit(Amount::ONE)
+        .expect_err("insufficient available funds");
+}
+
+#[test]
+fn negative_balance_hold_insufficient_available_funds() {
+    let mut balance = Balance::default();
+    balance
+        .hold(Amount::ONE)
+        .expect_err("insufficient available funds");
+}
+
+#[test]
+fn negative_balance_release_insufficient_funds_on_hold() {
+    let mut balance = Balance::default();
+    balance
+        .release(Amount::ONE)
+        .expect_err("insufficient funds on hold");
+}
+
+#[test]
+fn negative_balance_reimburse_insufficient_funds_on_hold() {
+    let mut balance = Balance::default();
+    balance
+        .reimburse(Amount::ONE)
+        .expect_err("insufficient funds on hold");
+}
+
+#[test]
+fn negative_balance_credit_overflow_leaves_balance_untouched() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::MAX)
+        .expect("an empty balance can hold the maximum amount");
+    assert_eq!(balance.credit(Amount::ONE), Err(BalanceError::Overflow));
+    assert_eq!(balance.available(), Amount::MAX);
+}
+
+#[test]
+fn negative_balance_credit_overflow_on_total() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::MAX)
+        .expect("an empty balance can hold the maximum amount");
+    balance
+        .hold(Amount::TEN)
+        .expect("there are sufficient available funds");
+    assert_eq!(balance.credit(Amount::ONE), Err(BalanceError::Overflow));
+    assert_eq!(balance.total(), Amount::MAX);
+}
+
+#[test]
+fn negative_balance_deserialization_overflow_on_total() {
+    let overflowing = r#"{"available":"79228162514264337593543950335","held":"1","overdrawn":"0"}"#;
+    assert!(serde_json::from_str::<Balance>(overflowing).is_err());
+
+    let valid = r#"{"available":"1","held":"1","overdrawn":"0"}"#;
+    let balance: Balance = serde_json::from_str(valid).expect("the total is representable");
+    assert_eq!(balance.total(), Amount::TWO);
+}
+
+// Ledger tests
+
+#[test]
+fn positive_ledger_routing() {
+    let mut ledger = Ledger::default();
+
+    let txns = [
+        Transaction {
+            t_type: Type::Deposit(Deposit::new(Amount::TEN)),
+            client: Client(1),
+            tx: Tx(1),
+            timestamp: None,
+        },
+        Transaction {
+            t_type: Type::Deposit(Deposit::new(Amount::TWO)),
+            client: Client(2),
+            tx: Tx(2),
+            timestamp: None,
+        },
+    ];
+
+    let _ = txns.map(|txn| ledger.apply(txn).expect("both txns are valid"));
+    assert_eq!(
+        ledger.get_account_for(Client(1)).balance.available(),
+        Amount::TEN
+    );
+    assert_eq!(
+        ledger.get_account_for(Client(2)).balance.available(),
+        Amount::TWO
+    );
+}
+
+#[test]
+fn positive_ledger_finalize_removes_account() {
+    let mut ledger = Ledger::default();
+    ledger
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("deposit is valid");
+
+    let account = ledger.finalize(C1).expect("account exists");
+    assert_eq!(account.balance.available(), Amount::TEN);
+    assert!(ledger.accounts.is_empty());
+}
+
+#[test]
+fn negative_ledger_rejects_transactions_for_finalized_accounts() {
+    let mut ledger = Ledger::default();
+    ledger
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("deposit is valid");
+    ledger.finalize(C1);
+
+    ledger
+        .apply(deposit(Tx(2), Amount::TEN))
+        .expect_err("the account is already finalized");
+    assert!(ledger.accounts.is_empty());
+}
+
+#[test]
+fn positive_ledger_dispute_within_window() {
+    let mut ledger = Ledger::default();
+    ledger.dispute_window = Some(DisputeWindow::Transactions(2));
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        deposit(Tx(2), Amount::TEN),
+        dispute(Tx(1)),
+    ];
+
+    for txn in txns {
+        ledger.apply(txn).expect("the deposit is within the window");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
+}
+
+#[test]
+fn negative_ledger_dispute_past_window_is_rejected() {
+    let mut ledger = Ledger::default();
+    ledger.dispute_window = Some(DisputeWindow::Transactions(2));
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        deposit(Tx(2), Amount::TEN),
+        deposit(Tx(3), Amount::TEN),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("deposits are valid");
+    }
+
+    let e = ledger
+        .apply(dispute(Tx(1)))
+        .expect_err("the deposit is past its dispute window");
+    assert_eq!(
+        e.downcast_ref::<DisputeWindowExpiredError>(),
+        Some(&DisputeWindowExpiredError(Tx(1)))
+    );
+    assert!(!ledger.get_account_for(C1).deposits.contains_key(&Tx(1)));
+
+    let e = ledger
+        .apply(dispute(Tx(9)))
+        .expect_err("the deposit never existed");
+    assert!(e.downcast_ref::<DisputeWindowExpiredError>().is_none());
+}
+
+#[test]
+fn positive_ledger_disputed_deposits_are_retained_past_window() {
+    let mut ledger = Ledger::default();
+    ledger.dispute_window = Some(DisputeWindow::Transactions(1));
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        dispute(Tx(1)),
+        deposit(Tx(2), Amount::ONE),
+        deposit(Tx(3), Amount::ONE),
+        resolve(Tx(1)),
+    ];
+
+    for txn in txns {
+        ledger
+            .apply(txn)
+            .expect("disputed deposits are not evicted");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::ZERO);
+}
+
+#[test]
+fn positive_ledger_dispute_within_window_duration() {
+    let mut ledger = Ledger::default();
+    ledger.dispute_window = Some(DisputeWindow::Duration(TimeDelta::days(30)));
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2024-01-01T00:00:00Z"),
+        deposit(Tx(2), Amount::TEN),
+        timed(deposit(Tx(3), Amount::TEN), "2024-01-30T23:59:59Z"),
+        timed(dispute(Tx(1)), "2024-01-30T23:59:59Z"),
+    ];
+
+    for txn in txns {
+        ledger.apply(txn).expect("the deposit is within the window");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
+}
+
+#[test]
+fn negative_ledger_dispute_past_window_duration_is_rejected() {
+    let mut ledger = Ledger::default();
+    ledger.dispute_window = Some(DisputeWindow::Duration(TimeDelta::days(30)));
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2024-01-01T00:00:00Z"),
+        deposit(Tx(2), Amount::TEN),
+        timed(deposit(Tx(3), Amount::TEN), "2024-01-31T00:00:00Z"),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("deposits are valid");
+    }
+
+    let e = ledger
+        .apply(timed(dispute(Tx(1)), "2024-01-31T00:00:00Z"))
+        .expect_err("the deposit is past its dispute window");
+    assert_eq!(
+        e.downcast_ref::<DisputeWindowExpiredError>(),
+        Some(&DisputeWindowExpiredError(Tx(1)))
+    );
+
+    // Deposits without a timestamp are retained forever
+    ledger
+        .apply(timed(dispute(Tx(2)), "2025-01-01T00:00:00Z"))
+        .expect("the deposit has no timestamp to expire from");
+}
+
+// Transfer tests
+
+#[test]
+fn positive_ledger_transfer_moves_funds() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        transfer(Tx(2), C2, Amount::TWO),
+    ];
+
+    for txn in txns {
+        ledger.apply(txn).expect("both txns are valid");
+    }
+    assert_eq!(
+        ledger.get_account_for(C1).balance.available(),
+        amount(dec!(8))
+    );
+    assert_eq!(ledger.get_account_for(C2).balance.available(), Amount::TWO);
+}
+
+#[test]
+fn negative_ledger_transfer_insufficient_funds() {
+    let mut ledger = Ledger::default();
+    ledger
+        .apply(deposit(Tx(1), Amount::ONE))
+        .expect("deposit is valid");
+
+    ledger
+        .apply(transfer(Tx(2), C2, Amount::TWO))
+        .expect_err("insufficient funds");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::ONE);
+    assert!(!ledger.accounts.contains_key(&C2));
+}
+
+#[test]
+fn negative_ledger_transfer_to_locked_account_is_rolled_back() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        Transaction {
+            client: C2,
+            ..deposit(Tx(2), Amount::ONE)
+        },
+        Transaction {
+            client: C2,
+            ..dispute(Tx(2))
+        },
+        Transaction {
+            client: C2,
+            ..chargeback(Tx(2))
+        },
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("all txns are valid");
+    }
+
+    ledger
+        .apply(transfer(Tx(3), C2, Amount::TWO))
+        .expect_err("the recipient is locked");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+    assert_eq!(ledger.get_account_for(C2).balance.total(), Amount::ZERO);
+}
+
+#[test]
+fn negative_ledger_transfer_from_locked_account() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        deposit(Tx(2), Amount::ONE),
+        dispute(Tx(2)),
+        chargeback(Tx(2)),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("all txns are valid");
+    }
+
+    ledger
+        .apply(transfer(Tx(3), C2, Amount::TWO))
+        .expect_err("the sender is locked");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+    assert!(!ledger.accounts.contains_key(&C2));
+}
+
+#[test]
+fn negative_ledger_transfer_overflowing_recipient() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        Transaction {
+            client: C2,
+            ..deposit(Tx(2), Amount::MAX)
+        },
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("both deposits are valid");
+    }
+
+    ledger
+        .apply(transfer(Tx(3), C2, Amount::ONE))
+        .expect_err("the recipient can't hold more funds");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+}
+
+#[test]
+fn negative_ledger_transfer_to_self_or_finalized_account() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        Transaction {
+            client: C2,
+            ..deposit(Tx(2), Amount::ONE)
+        },
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("both deposits are valid");
+    }
+    ledger.finalize(C2);
+
+    ledger
+        .apply(transfer(Tx(3), C1, Amount::ONE))
+        .expect_err("can't transfer to the same client");
+    ledger
+        .apply(transfer(Tx(4), C2, Amount::ONE))
+        .expect_err("the recipient is already finalized");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+}
+
+#[test]
+fn negative_ledger_transfers_are_not_disputable() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        transfer(Tx(2), C2, Amount::TWO),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("both txns are valid");
+    }
+
+    ledger
+        .apply(dispute(Tx(2)))
+        .expect_err("the sender can't dispute a transfer");
+    ledger
+        .apply(Transaction {
+            client: C2,
+            ..dispute(Tx(2))
+        })
+        .expect_err("the recipient can't dispute a transfer");
+    assert_eq!(ledger.get_account_for(C2).balance.held(), Amount::ZERO);
+}
+
+// Fee tests
+
+#[test]
+fn positive_fee_flat_plus_percent_within_caps() {
+    let fee = Fee {
+        flat: amount(dec!(0.5)),
+        percent: amount(dec!(1)),
+        min: Some(Amount::ONE),
+        max: Some(Amount::TEN),
+    };
+
+    assert_eq!(fee.compute(amount(dec!(100))), Ok(amount(dec!(1.5))));
+    assert_eq!(fee.compute(amount(dec!(10))), Ok(Amount::ONE));
+    assert_eq!(fee.compute(amount(dec!(10000))), Ok(Amount::TEN));
+    assert_eq!(Fee::default().compute(amount(dec!(100))), Ok(Amount::ZERO));
+}
+
+#[test]
+fn positive_ledger_posts_fees_to_house_account() {
+    let mut ledger = fee_ledger();
+    let txns = [
+        deposit(Tx(1), amount(dec!(100))),
+        withdrawal(Tx(2), amount(dec!(50))),
+        transfer(Tx(3), C2, Amount::TEN),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("all txns are valid");
+    }
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(account.balance.available(), amount(dec!(37)));
+    assert_eq!(account.fees, amount(dec!(3)));
+    assert_eq!(
+        ledger.get_account_for(HOUSE).balance.available(),
+        amount(dec!(3))
+    );
+    assert_eq!(ledger.get_account_for(C2).balance.available(), Amount::TEN);
+}
+
+#[test]
+fn negative_ledger_withdrawal_must_cover_its_fee() {
+    let mut ledger = fee_ledger();
+    ledger
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("deposit is valid");
+
+    ledger
+        .apply(withdrawal(Tx(2), Amount::TEN))
+        .expect_err("insufficient funds to cover the fee");
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+    assert!(!ledger.accounts.contains_key(&HOUSE));
+}
+
+#[test]
+fn negative_ledger_waives_fees_when_house_account_is_locked() {
+    let mut ledger = fee_ledger();
+    ledger.get_account_for(HOUSE).lock();
+    ledger
+        .apply(deposit(Tx(1), amount(dec!(100))))
+        .expect("deposit is valid");
+    ledger
+        .apply(withdrawal(Tx(2), amount(dec!(50))))
+        .expect("the fee is waived rather than rejecting the withdrawal");
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(account.balance.available(), amount(dec!(50)));
+    assert_eq!(account.fees, Amount::ZERO);
+    assert_eq!(ledger.get_account_for(HOUSE).balance.total(), Amount::ZERO);
+    assert_eq!(
+        ledger.alerts,
+        vec![Alert {
+            client: C1,
+            tx: Tx(2),
+            rule: AlertRule::FeeWaived,
+            severity: Severity::Warning,
+        }]
+    );
+}
+
+#[test]
+fn negative_ledger_waives_fees_when_house_account_is_finalized() {
+    let mut ledger = fee_ledger();
+    ledger.finalized.insert(HOUSE);
+    ledger
+        .apply(deposit(Tx(1), amount(dec!(100))))
+        .expect("deposit is valid");
+    ledger
+        .apply(withdrawal(Tx(2), amount(dec!(50))))
+        .expect("the fee is waived rather than rejecting the withdrawal");
+
+    assert_eq!(
+        ledger.get_account_for(C1).balance.available(),
+        amount(dec!(50))
+    );
+    assert!(!ledger.accounts.contains_key(&HOUSE));
+    assert_eq!(ledger.alerts.len(), 1);
+}
+
+#[test]
+fn positive_ledger_chargeback_fee_is_capped_at_available_funds() {
+    let mut ledger = fee_ledger();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        deposit(Tx(2), Amount::ONE),
+        dispute(Tx(1)),
+        chargeback(Tx(1)),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("all txns are valid");
+    }
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(account.balance.total(), Amount::ZERO);
+    assert_eq!(account.fees, Amount::ONE);
+    assert_eq!(
+        ledger.get_account_for(HOUSE).balance.available(),
+        Amount::ONE
+    );
+}
+
+#[test]
+fn positive_ledger_percent_fee_on_huge_deposit() {
+    let mut ledger = fee_ledger();
+    if let Some(fees) = &mut ledger.fee_schedule {
+        fees.deposit = Some(Fee {
+            percent: Amount::TWO,
+            ..Fee::default()
+        });
+    }
+    ledger
+        .apply(deposit(
+            Tx(1),
+            amount(dec!(70_000_000_000_000_000_000_000_000_000)),
+        ))
+        .expect("deposit is valid");
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(
+        account.balance.available(),
+        amount(dec!(68_600_000_000_000_000_000_000_000_000))
+    );
+    assert_eq!(
+        account.fees,
+        amount(dec!(1_400_000_000_000_000_000_000_000_000))
+    );
+}
+
+#[test]
+fn negative_ledger_fee_overflow_is_waived() {
+    let mut ledger = fee_ledger();
+    if let Some(fees) = &mut ledger.fee_schedule {
+        fees.deposit = Some(Fee {
+            flat: amount(dec!(79_000_000_000_000_000_000_000_000_000)),
+            percent: amount(dec!(50)),
+            ..Fee::default()
+        });
+    }
+    ledger
+        .apply(deposit(
+            Tx(1),
+            amount(dec!(70_000_000_000_000_000_000_000_000_000)),
+        ))
+        .expect("deposit is valid");
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(
+        account.balance.available(),
+        amount(dec!(70_000_000_000_000_000_000_000_000_000))
+    );
+    assert_eq!(account.fees, Amount::ZERO);
+    assert!(!ledger.accounts.contains_key(&HOUSE));
+}
+
+#[test]
+fn negative_ledger_house_account_is_never_finalized() {
+    let mut ledger = fee_ledger();
+    ledger
+        .apply(Transaction {
+            client: HOUSE,
+            ..deposit(Tx(1), Amount::TEN)
+        })
+        .expect("deposit is valid");
+
+    assert!(ledger.finalize(HOUSE).is_none());
+    assert_eq!(ledger.get_account_for(HOUSE).fees, Amount::ZERO);
+}
+
+// Credit limit tests
+
+#[test]
+fn positive_balance_debit_with_limit_overdraws() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TWO)
+        .expect("an empty balance can be credited");
+    balance
+        .debit_with_limit(Amount::TEN, Amount::TEN)
+        .expect("the debit is within the limit");
+
+    assert_eq!(balance.available(), Amount::ZERO);
+    assert_eq!(balance.overdrawn(), amount(dec!(8)));
+    assert_eq!(
+        balance.signed_available(),
+        Amount::ZERO.signed_sub(amount(dec!(8)))
+    );
+    assert_eq!(
+        balance.debit_with_limit(amount(dec!(3)), Amount::TEN),
+        Err(BalanceError::InsufficientFunds)
+    );
+    assert_eq!(balance.overdrawn(), amount(dec!(8)));
+}
+
+#[test]
+fn positive_balance_credit_and_release_repay_overdrawn_funds_first() {
+    let mut balance = Balance::default();
+    balance
+        .credit(Amount::TEN)
+        .expect("an empty balance can be credited");
+    balance
+        .hold(Amount::TEN)
+        .expect("there are sufficient available funds");
+    balance
+        .debit_with_limit(Amount::TEN, Amount::TEN)
+        .expect("the debit is within the limit");
+    assert_eq!(
+        balance.signed_total(),
+        Amount::ZERO.signed_sub(Amount::ZERO)
+    );
+
+    balance
+        .credit(Amount::TWO)
+        .expect("the balance can be credited");
+    assert_eq!(balance.overdrawn(), amount(dec!(8)));
+    assert_eq!(balance.available(), Amount::ZERO);
+
+    balance
+        .release(Amount::TEN)
+        .expect("there are sufficient funds on hold");
+    assert_eq!(balance.overdrawn(), Amount::ZERO);
+    assert_eq!(balance.available(), Amount::TWO);
+}
+
+#[test]
+fn negative_balance_debit_without_limit_does_not_overdraw() {
+    let mut balance = Balance::default();
+    assert_eq!(
+        balance.debit_with_limit(Amount::ONE, Amount::ZERO),
+        Err(BalanceError::InsufficientFunds)
+    );
+    assert_eq!(balance, Balance::default());
+}
+
+#[test]
+fn positive_ledger_applies_credit_limits() {
+    let mut ledger = Ledger::default();
+    ledger.credit_limits.insert(C1, Amount::TEN);
+    let txns = [
+        deposit(Tx(1), Amount::ONE),
+        withdrawal(Tx(2), Amount::TEN),
+        Transaction {
+            client: C2,
+            ..deposit(Tx(3), Amount::ONE)
+        },
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("all txns are valid");
+    }
+
+    assert_eq!(
+        ledger.get_account_for(C1).balance.overdrawn(),
+        amount(dec!(9))
+    );
+    ledger
+        .apply(transfer(Tx(4), C2, Amount::TWO))
+        .expect_err("the transfer exceeds the limit");
+    ledger
+        .apply(Transaction {
+            client: C2,
+            ..withdrawal(Tx(5), Amount::TWO)
+        })
+        .expect_err("client 2 has no credit line");
+    assert_eq!(ledger.get_account_for(C2).credit_limit, Amount::ZERO);
+}
+
+// Risk limit tests
+
+#[test]
+fn negative_account_withdrawal_above_max_is_rejected() {
+    let mut account = risky_account(RiskLimits {
+        max_withdrawal: Some(Amount::TWO),
+        ..RiskLimits::default()
+    });
+
+    let e = account
+        .apply(withdrawal(Tx(2), Amount::TEN))
+        .expect_err("the withdrawal is above the maximum");
+    assert_eq!(
+        e.downcast_ref::<RiskLimitError>(),
+        Some(&RiskLimitError::MaxWithdrawal(Tx(2)))
+    );
+    account
+        .apply(transfer(Tx(3), C2, Amount::TEN))
+        .expect_err("transfers are outflows too");
+    account
+        .apply(withdrawal(Tx(4), Amount::TWO))
+        .expect("the withdrawal is within the maximum");
+    assert!(!account.locked);
+}
+
+#[test]
+fn negative_account_withdrawals_above_velocity_are_rejected() {
+    let mut account = risky_account(RiskLimits {
+        max_withdrawals: Some(Velocity {
+            count: 2,
+            window: TimeDelta::hours(1),
+        }),
+        ..RiskLimits::default()
+    });
+    let txns = [
+        timed(withdrawal(Tx(2), Amount::ONE), "2026-09-01T10:00:00Z"),
+        timed(withdrawal(Tx(3), Amount::ONE), "2026-09-01T10:30:00Z"),
+        withdrawal(Tx(4), Amount::ONE), // <- Undated outflows can't be placed in the window
+    ];
+    for txn in txns {
+        account.apply(txn).expect("within the velocity limit");
+    }
+
+    let e = account
+        .apply(timed(
+            withdrawal(Tx(5), Amount::ONE),
+            "2026-09-01T10:59:59Z",
+        ))
+        .expect_err("third withdrawal within the hour");
+    assert_eq!(
+        e.downcast_ref::<RiskLimitError>(),
+        Some(&RiskLimitError::MaxWithdrawals(Tx(5)))
+    );
+    account
+        .apply(timed(
+            withdrawal(Tx(6), Amount::ONE),
+            "2026-09-01T11:00:00Z",
+        ))
+        .expect("the first withdrawal left the window");
+}
+
+#[test]
+fn negative_account_daily_outflow_above_max_is_rejected() {
+    let mut account = risky_account(RiskLimits {
+        max_daily_outflow: Some(Amount::TWO),
+        ..RiskLimits::default()
+    });
+    account
+        .apply(timed(
+            withdrawal(Tx(2), Amount::ONE),
+            "2026-09-01T10:00:00Z",
+        ))
+        .expect("within the daily outflow");
+
+    let e = account
+        .apply(timed(
+            transfer(Tx(3), C2, Amount::TWO),
+            "2026-09-01T23:00:00Z",
+        ))
+        .expect_err("above the daily outflow");
+    assert_eq!(
+        e.downcast_ref::<RiskLimitError>(),
+        Some(&RiskLimitError::MaxDailyOutflow(Tx(3)))
+    );
+    account
+        .apply(timed(
+            withdrawal(Tx(4), Amount::TWO),
+            "2026-09-02T00:00:00Z",
+        ))
+        .expect("the outflow is reset every day");
+}
+
+#[test]
+fn negative_account_is_locked_on_breach_if_configured() {
+    let mut account = risky_account(RiskLimits {
+        max_withdrawal: Some(Amount::ONE),
+        lock_on_breach: true,
+        ..RiskLimits::default()
+    });
+
+    account
+        .apply(withdrawal(Tx(2), Amount::TWO))
+        .expect_err("the withdrawal is above the maximum");
+    assert!(account.locked);
+    assert_eq!(account.balance.available(), Amount::TEN);
+}
+
+#[test]
+fn positive_ledger_applies_client_risk_limits_over_default_ones() {
+    let mut ledger = Ledger::default();
+    ledger.risk_limits = RiskConfig {
+        default: Some(RiskLimits {
+            max_withdrawal: Some(Amount::ONE),
+            ..RiskLimits::default()
+        }),
+        clients: [(C2, RiskLimits::default())].into(),
+    };
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        Transaction {
+            client: C2,
+            ..deposit(Tx(2), Amount::TEN)
+        },
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("both deposits are valid");
+    }
+
+    ledger
+        .apply(withdrawal(Tx(3), Amount::TWO))
+        .expect_err("client 1 has the default limits");
+    ledger
+        .apply(Transaction {
+            client: C2,
+            ..withdrawal(Tx(4), Amount::TWO)
+        })
+        .expect("client 2 has its own limits");
+}
+
+// Validator tests
+
+#[test]
+fn negative_ledger_validator_rejects_outflows_from_new_accounts() {
+    let mut ledger = Ledger::default();
+    ledger
+        .validators
+        .push(Box::new(NewAccountOutflows::new(TimeDelta::days(7))));
+    ledger
+        .apply(timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"))
+        .expect("deposits are not checked");
+
+    let e = ledger
+        .apply(timed(
+            withdrawal(Tx(2), Amount::ONE),
+            "2026-09-07T23:59:59Z",
+        ))
+        .expect_err("the account is too new");
+    assert_eq!(e.downcast_ref::<RejectedError>().map(|e| e.tx), Some(Tx(2)));
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
+
+    ledger
+        .apply(timed(
+            withdrawal(Tx(3), Amount::ONE),
+            "2026-09-08T00:00:00Z",
+        ))
+        .expect("the account is old enough");
+}
+
+#[test]
+fn positive_ledger_validator_flags_round_deposits() {
+    let mut ledger = Ledger::default();
+    ledger.validators.push(Box::new(RoundDeposits {
+        multiple: Amount::TEN,
+    }));
+    let txns = [
+        deposit(Tx(1), amount(dec!(100))),
+        deposit(Tx(2), amount(dec!(10.5))),
+    ];
+    for txn in txns {
+        ledger
+            .apply(txn)
+            .expect("flagged deposits are still applied");
+    }
+
+    assert_eq!(
+        ledger.flags,
+        vec![Flag {
+            client: C1,
+            tx: Tx(1),
+            reason: "round deposit, a multiple of 10".to_string(),
+        }]
+    );
+    assert_eq!(
+        ledger.get_account_for(C1).balance.available(),
+        amount(dec!(110.5))
+    );
+}
+
+#[test]
+fn positive_ledger_consults_custom_validators_in_order() {
+    /// Rejects any transaction after the first `limit` ones, and flags the rest
+    #[derive(Debug)]
+    struct Quota {
+        limit: usize,
+        seen: usize,
+    }
+
+    impl TransactionValidator for Quota {
+        fn before(&mut self, _account: &Account, _txn: &Transaction) -> Verdict {
+            self.seen += 1;
+            if self.seen > self.limit {
+                Verdict::Reject("over quota".to_string())
+            } else {
+                Verdict::Flag(format!("{} of {}", self.seen, self.limit))
+            }
+        }
+    }
+
+    let mut ledger = Ledger::default();
+    ledger
+        .validators
+        .push(Box::new(Quota { limit: 1, seen: 0 }));
+    ledger
+        .validators
+        .push(Box::new(Quota { limit: 2, seen: 0 }));
+    ledger
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("both validators accept it");
+    ledger
+        .apply(deposit(Tx(2), Amount::TEN))
+        .expect_err("the first validator rejects it");
+
+    let reasons: Vec<String> = ledger.flags.into_iter().map(|f| f.reason).collect();
+    assert_eq!(reasons, ["1 of 1", "1 of 2"]);
+}
+
+#[test]
+fn negative_ledger_drops_flags_of_rejected_transactions() {
+    /// Flags every withdrawal
+    #[derive(Debug)]
+    struct Withdrawals;
+
+    impl TransactionValidator for Withdrawals {
+        fn before(&mut self, _account: &Account, txn: &Transaction) -> Verdict {
+            match txn.t_type {
+                Type::Withdrawal(_) => Verdict::Flag("withdrawal".to_string()),
+                _ => Verdict::Accept,
+            }
+        }
+    }
+
+    let mut ledger = Ledger::default();
+    ledger.validators.push(Box::new(Withdrawals));
+    ledger
+        .apply(deposit(Tx(1), Amount::ONE))
+        .expect("valid deposit");
+    ledger
+        .apply(withdrawal(Tx(2), Amount::TWO))
+        .expect_err("insufficient available funds");
+    assert_eq!(ledger.flags, vec![]);
+
+    ledger
+        .apply(withdrawal(Tx(3), Amount::ONE))
+        .expect("valid withdrawal");
+    assert_eq!(
+        ledger.flags,
+        vec![Flag {
+            client: C1,
+            tx: Tx(3),
+            reason: "withdrawal".to_string(),
+        }]
+    );
+}
+
+// Alert tests
+#[test]
+fn positive_ledger_alerts_on_dispute_of_withdrawn_funds() {
+    let mut ledger = Ledger::default();
+    ledger
+        .apply(deposit(Tx(1), Amount::TWO))
+        .expect("valid deposit");
+    ledger
+        .apply(withdrawal(Tx(2), Amount::ONE))
+        .expect("valid withdrawal");
+    ledger
+        .apply(dispute(Tx(1)))
+        .expect_err("funds were withdrawn");
+
+    assert_eq!(
+        ledger.alerts,
+        vec![Alert {
+            client: C1,
+            tx: Tx(1),
+            rule: AlertRule::DisputeInsufficientFunds,
+            severity: Severity::Warning,
+        }]
+    );
+}
+
+#[test]
+fn positive_ledger_alerts_once_on_locked_account() {
+    let mut ledger = Ledger::default();
+    ledger
+        .apply(deposit(Tx(1), Amount::TWO))
+        .expect("valid deposit");
+    ledger.apply(dispute(Tx(1))).expect("valid dispute");
+    ledger.apply(chargeback(Tx(1))).expect("valid chargeback");
+    ledger
+        .apply(deposit(Tx(2), Amount::ONE))
+        .expect_err("account is locked");
+
+    assert_eq!(
+        ledger.alerts,
+        vec![Alert {
+            client: C1,
+            tx: Tx(1),
+            rule: AlertRule::AccountLocked,
+            severity: Severity::Critical,
+        }]
+    );
+}
+
+#[test]
+fn positive_ledger_alerts_on_account_locked_by_expired_dispute() {
+    let mut ledger = expiring_ledger(ExpiryPolicy::Chargeback);
+    let txns = [
+        timed(deposit(Tx(1), Amount::TWO), "2026-09-01T00:00:00Z"),
+        timed(dispute(Tx(1)), "2026-09-01T00:00:00Z"),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+    ledger
+        .apply(timed(deposit(Tx(2), Amount::ONE), "2026-09-05T00:00:00Z"))
+        .expect_err("account is locked by the expired dispute");
+
+    assert_eq!(
+        ledger.alerts,
+        vec![Alert {
+            client: C1,
+            tx: Tx(1),
+            rule: AlertRule::AccountLocked,
+            severity: Severity::Critical,
+        }]
+    );
+}
+
+#[test]
+fn positive_ledger_alerts_on_repeated_rejected_withdrawals() {
+    let mut ledger = Ledger::default();
+    ledger.rejected_withdrawals_alert = Some(2);
+    ledger
+        .apply(withdrawal(Tx(1), Amount::ONE))
+        .expect_err("insufficient funds");
+    assert!(ledger.alerts.is_empty());
+    ledger
+        .apply(transfer(Tx(2), C2, Amount::ONE))
+        .expect_err("insufficient funds");
+    ledger
+        .apply(withdrawal(Tx(3), Amount::ONE))
+        .expect_err("insufficient funds");
+
+    assert_eq!(
+        ledger.alerts,
+        vec![Alert {
+            client: C1,
+            tx: Tx(2),
+            rule: AlertRule::RepeatedRejectedWithdrawals,
+            severity: Severity::Warning,
+        }]
+    );
+}
+
+#[test]
+fn negative_ledger_successful_withdrawal_resets_rejected_withdrawals() {
+    let mut ledger = Ledger::default();
+    ledger.rejected_withdrawals_alert = Some(2);
+    ledger
+        .apply(withdrawal(Tx(1), Amount::ONE))
+        .expect_err("insufficient funds");
+    ledger
+        .apply(deposit(Tx(2), Amount::ONE))
+        .expect("valid deposit");
+    ledger
+        .apply(withdrawal(Tx(3), Amount::ONE))
+        .expect("valid withdrawal");
+    ledger
+        .apply(withdrawal(Tx(4), Amount::ONE))
+        .expect_err("insufficient funds");
+
+    assert!(ledger.alerts.is_empty());
+}
+
+// Observer tests
+#[test]
+fn positive_observer_notified_of_applied_and_rejected_transactions() {
+    let (mut ledger, events) = observed_ledger();
+    ledger
+        .apply(deposit(Tx(1), Amount::TWO))
+        .expect("valid deposit");
+    ledger
+        .apply(withdrawal(Tx(2), Amount::TEN))
+        .expect_err("insufficient funds");
+
+    let credited = balance(Amount::TWO, Amount::ZERO);
+    assert_eq!(
+        *events.lock().expect("no panic while recording"),
+        vec![
+            Observed::Applied(
+                Tx(1),
+                vec![BalanceChange {
+                    client: C1,
+                    before: Balance::default(),
+                    after: credited,
+                }]
+            ),
+            Observed::Rejected(Tx(2)),
+        ]
+    );
+}
+
+#[test]
+fn positive_observer_notified_of_both_sides_of_a_transfer() {
+    let (mut ledger, events) = observed_ledger();
+    ledger
+        .apply(deposit(Tx(1), Amount::TWO))
+        .expect("valid deposit");
+    events.lock().expect("no panic while recording").clear();
+    ledger
+        .apply(transfer(Tx(2), C2, Amount::ONE))
+        .expect("valid transfer");
+
+    let one = balance(Amount::ONE, Amount::ZERO);
+    assert_eq!(
+        *events.lock().expect("no panic while recording"),
+        vec![Observed::Applied(
+            Tx(2),
+            vec![
+                BalanceChange {
+                    client: C1,
+                    before: balance(Amount::TWO, Amount::ZERO),
+                    after: one,
+                },
+                BalanceChange {
+                    client: C2,
+                    before: Balance::default(),
+                    after: one,
+                },
+            ]
+        )]
+    );
+}
+
+#[test]
+fn positive_observer_notified_of_dispute_transitions_and_locks() {
+    let (mut ledger, events) = observed_ledger();
+    ledger
+        .apply(deposit(Tx(1), Amount::TWO))
+        .expect("valid deposit");
+    ledger.apply(dispute(Tx(1))).expect("valid dispute");
+    ledger.apply(chargeback(Tx(1))).expect("valid chargeback");
+
+    let credited = balance(Amount::TWO, Amount::ZERO);
+    let held = balance(Amount::ZERO, Amount::TWO);
+    let charged_back = BalanceChange {
+        client: C1,
+        before: held,
+        after: Balance::default(),
+    };
+    assert_eq!(
+        events.lock().expect("no panic while recording")[2..],
+        [
+            Observed::Transition(
+                Tx(1),
+                DisputeStatus::Open,
+                BalanceChange {
+                    client: C1,
+                    before: credited,
+                    after: held,
+                }
+            ),
+            Observed::Applied(Tx(1), vec![charged_back]),
+            Observed::Transition(Tx(1), DisputeStatus::ChargedBack, charged_back),
+            Observed::Locked(Tx(1)),
+        ]
+    );
+}
+
+#[test]
+fn positive_observer_notified_of_expired_disputes() {
+    let (mut ledger, events) = observed_ledger();
+    ledger.dispute_expiry = Some(DisputeExpiry {
+        after: TimeDelta::days(1),
+        policy: ExpiryPolicy::Resolve,
+    });
+    ledger
+        .apply(timed(deposit(Tx(1), Amount::TWO), "2026-09-01T00:00:00Z"))
+        .expect("valid deposit");
+    ledger
+        .apply(timed(dispute(Tx(1)), "2026-09-01T00:00:00Z"))
+        .expect("valid dispute");
+    events.lock().expect("no panic while recording").clear();
+    ledger.expire_disputes(at("2026-09-03T00:00:00Z"));
+
+    assert_eq!(
+        *events.lock().expect("no panic while recording"),
+        vec![Observed::Transition(
+            Tx(1),
+            DisputeStatus::Resolved,
+            BalanceChange {
+                client: C1,
+                before: balance(Amount::ZERO, Amount::TWO),
+                after: balance(Amount::TWO, Amount::ZERO),
+            }
+        )]
+    );
+}
+
+#[test]
+fn positive_observer_notified_once_of_lock_by_expired_dispute() {
+    let (mut ledger, events) = observed_ledger();
+    ledger.dispute_expiry = Some(DisputeExpiry {
+        after: TimeDelta::days(1),
+        policy: ExpiryPolicy::Chargeback,
+    });
+    ledger
+        .apply(timed(deposit(Tx(1), Amount::TWO), "2026-09-01T00:00:00Z"))
+        .expect("valid deposit");
+    ledger
+        .apply(timed(dispute(Tx(1)), "2026-09-01T00:00:00Z"))
+        .expect("valid dispute");
+    ledger
+        .apply(timed(deposit(Tx(2), Amount::ONE), "2026-09-03T00:00:00Z"))
+        .expect_err("account is locked by the expired dispute");
+
+    assert_eq!(
+        events.lock().expect("no panic while recording")[3..],
+        [
+            Observed::Transition(
+                Tx(1),
+                DisputeStatus::ChargedBack,
+                BalanceChange {
+                    client: C1,
+                    before: balance(Amount::ZERO, Amount::TWO),
+                    after: Balance::default(),
+                }
+            ),
+            Observed::Locked(Tx(1)),
+            Observed::Rejected(Tx(2)),
+        ]
+    );
+}
+
+// Partial dispute tests
+
+#[test]
+fn positive_account_sequential_partial_disputes() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        partial(dispute, Tx(1), dec!(3)),
+        partial(dispute, Tx(1), dec!(5)),
+        dispute(Tx(1)), // <- Disputes whatever is left
+    ];
+
+    for txn in &txns[..3] {
+        account.apply(*txn).expect("txns are valid");
+    }
+    assert_eq!(account.balance.available(), Amount::TWO);
+    assert_eq!(account.balance.held(), amount(dec!(8)));
+
+    account
+        .apply(txns[3])
+        .expect("there's still 2 left to dispute");
+    assert_eq!(account.balance.available(), Amount::ZERO);
+    assert_eq!(account.balance.held(), Amount::TEN);
+}
+
+#[test]
+fn negative_account_partial_dispute_exceeding_deposit() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        deposit(Tx(2), Amount::TEN),
+        partial(dispute, Tx(1), dec!(6)),
+        partial(dispute, Tx(1), dec!(5)),
+    ];
+
+    for txn in &txns[..3] {
+        account.apply(*txn).expect("txns are valid");
+    }
+    account
+        .apply(txns[3])
+        .expect_err("can't dispute more than the deposit amount");
+    assert_eq!(account.balance.held(), amount(dec!(6)));
+    assert_eq!(account.deposits[&Tx(1)].disputed, amount(dec!(6)));
+}
+
+#[test]
+fn positive_account_partial_resolve() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        dispute(Tx(1)),
+        partial(resolve, Tx(1), dec!(4)),
+        resolve(Tx(1)),
+    ];
+
+    for txn in &txns[..3] {
+        account.apply(*txn).expect("txns are valid");
+    }
+    assert_eq!(account.balance.available(), amount(dec!(4)));
+    assert_eq!(account.balance.held(), amount(dec!(6)));
+    assert!(account.deposits[&Tx(1)].is_disputed());
+
+    account.apply(txns[3]).expect("the rest can be resolved");
+    assert_eq!(account.balance.available(), Amount::TEN);
+    assert!(!account.deposits[&Tx(1)].is_disputed());
+}
+
+#[test]
+fn positive_account_partial_chargeback_releases_the_rest() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        dispute(Tx(1)),
+        partial(chargeback, Tx(1), dec!(4)),
+    ];
+
+    for txn in txns {
+        account.apply(txn).expect("txns are valid");
+    }
+    assert_eq!(account.balance.available(), amount(dec!(6)));
+    assert_eq!(account.balance.held(), Amount::ZERO);
+    assert_eq!(account.deposits[&Tx(1)].charged_back, amount(dec!(4)));
+    assert!(account.locked);
+}
+
+#[test]
+fn negative_account_partial_resolve_exceeding_disputed_amount() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        partial(dispute, Tx(1), dec!(2)),
+        partial(resolve, Tx(1), dec!(3)),
+    ];
+
+    for txn in &txns[..2] {
+        account.apply(*txn).expect("txns are valid");
+    }
+    account
+        .apply(txns[2])
+        .expect_err("can't resolve more than the disputed amount");
+    assert_eq!(account.balance.held(), Amount::TWO);
+}
+
+#[test]
+fn positive_account_failed_dispute_leaves_deposit_undisputed() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        withdrawal(Tx(2), Amount::ONE),
+        dispute(Tx(1)),
+        deposit(Tx(3), Amount::ONE),
+        dispute(Tx(1)),
+    ];
+
+    for txn in &txns[..2] {
+        account.apply(*txn).expect("txns are valid");
+    }
+    account
+        .apply(txns[2])
+        .expect_err("insufficient funds to hold");
+    assert!(!account.deposits[&Tx(1)].is_disputed());
 
-use super::{
-    common::{Amount, Client, Tx},
-    ledger::Ledger,
-    transaction::{Deposit, DisputeStatus, Transaction, Type, Withdrawal},
-};
+    for txn in &txns[3..] {
+        account
+            .apply(*txn)
+            .expect("the deposit can be disputed once funds are back");
+    }
+    assert_eq!(account.balance.held(), Amount::TEN);
+}
 
-// Amount tests
 #[test]
-fn negative_amount_allow_only_nonnegative_values() {
-    Amount::try_from(dec!(-1)).expect_err("only non-negative values allowed");
+fn positive_ledger_ignores_dispute_amounts_by_default() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        partial(dispute, Tx(1), dec!(3)),
+    ];
+
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
 }
 
-// Balance tests
 #[test]
-fn positive_balance_credit() {
-    let mut balance = Balance::default();
-    balance.credit(Amount::TEN);
-    assert_eq!(balance.available(), Amount::TEN);
-    assert_eq!(balance.held(), Amount::ZERO);
+fn positive_ledger_ignores_invalid_dispute_amounts_by_default() {
+    let mut ledger = Ledger::default();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        partial(dispute, Tx(1), dec!(-3)),
+        partial(resolve, Tx(1), dec!(-3)),
+    ];
+
+    for txn in txns {
+        ledger
+            .apply(txn)
+            .expect("the amounts are dropped before being checked");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.available(), Amount::TEN);
 }
 
 #[test]
-fn positive_balance_debit() {
-    let mut balance = Balance::default();
-    balance.credit(Amount::TEN);
-    balance
-        .debit(Amount::TWO)
-        .expect("there are sufficient available funds");
-    assert_eq!(balance.available(), Amount::TEN - Amount::TWO);
-    assert_eq!(balance.held(), Amount::ZERO);
+fn negative_ledger_rejects_invalid_dispute_amounts_if_enabled() {
+    let mut ledger = Ledger::default();
+    ledger.partial_disputes = true;
+    ledger
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("valid deposit");
+    ledger.apply(dispute(Tx(1))).expect("valid dispute");
+
+    let e = ledger
+        .apply(partial(chargeback, Tx(1), dec!(-3)))
+        .expect_err("negative amounts are not allowed");
+    assert!(e.downcast_ref::<NegativeAmountError>().is_some());
+    let account = ledger.get_account_for(C1);
+    assert!(!account.locked);
+    assert_eq!(account.balance.held(), Amount::TEN);
 }
 
 #[test]
-fn positive_balance_hold() {
-    let mut balance = Balance::default();
-    balance.credit(Amount::TEN);
-    balance
-        .hold(Amount::TWO)
-        .expect("there are available sufficient funds");
-    assert_eq!(balance.available(), Amount::TEN - Amount::TWO);
-    assert_eq!(balance.held(), Amount::TWO);
+fn positive_ledger_honors_dispute_amounts_if_enabled() {
+    let mut ledger = Ledger::default();
+    ledger.partial_disputes = true;
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        partial(dispute, Tx(1), dec!(3)),
+    ];
+
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), amount(dec!(3)));
 }
 
+// Dispute lifecycle tests
+
 #[test]
-fn positive_balance_release() {
-    let mut balance = Balance::default();
-    balance.credit(Amount::TEN);
-    balance
-        .hold(Amount::TWO)
-        .expect("there are sufficient available funds");
-    balance
-        .release(Amount::ONE)
-        .expect("there are sufficient funds on hold");
-    assert_eq!(balance.available(), Amount::TEN - Amount::TWO + Amount::ONE);
-    assert_eq!(balance.held(), Amount::TWO - Amount::ONE);
+fn positive_deposit_dispute_lifecycle() {
+    let mut deposit = Deposit::new(Amount::TEN);
+    assert_eq!(deposit.dispute_status, DisputeStatus::None);
+
+    deposit.open_dispute(None).expect("never disputed");
+    assert_eq!(deposit.dispute_status, DisputeStatus::Open);
+    deposit.resolve_dispute(None).expect("disputed");
+    assert_eq!(deposit.dispute_status, DisputeStatus::Resolved);
+    deposit.open_dispute(None).expect("resolved");
+    assert_eq!(deposit.dispute_status, DisputeStatus::Reopened);
+    deposit.charge_back(None).expect("disputed");
+    assert_eq!(deposit.dispute_status, DisputeStatus::ChargedBack);
 }
 
 #[test]
-fn positive_balance_reimburse() {
-    let mut balance = Balance::default();
-    balance.credit(Amount::TEN);
-    balance
-        .hold(Amount::TWO)
-        .expect("there are sufficient available funds");
-    balance
-        .reimburse(Amount::ONE)
-        .expect("there are sufficient funds on hold");
-    assert_eq!(balance.available(), Amount::TEN - Amount::TWO);
-    assert_eq!(balance.held(), Amount::TWO - Amount::ONE);
+fn negative_deposit_no_dispute_after_chargeback() {
+    let mut deposit = Deposit::new(Amount::TEN);
+    deposit
+        .open_dispute(Some(Amount::TWO))
+        .expect("never disputed");
+    deposit.charge_back(None).expect("disputed");
+
+    deposit
+        .open_dispute(None)
+        .expect_err("a charged back deposit can't be disputed again");
+    assert_eq!(deposit.dispute_status, DisputeStatus::ChargedBack);
 }
 
-// Negative balance tests
+#[test]
+fn negative_deposit_no_resolve_without_dispute() {
+    let mut deposit = Deposit::new(Amount::TEN);
+    deposit.resolve_dispute(None).expect_err("never disputed");
+    deposit.open_dispute(None).expect("never disputed");
+    deposit.resolve_dispute(None).expect("disputed");
+    deposit.charge_back(None).expect_err("already resolved");
+}
 
 #[test]
-fn negative_balance_debit_insufficient_available_funds() {
-    let mut balance = Balance::default();
-    balance
-        .debit(Amount::ONE)
-        .expect_err("insufficient available funds");
+fn positive_account_dispute_history() {
+    let mut account = default_account();
+    let txns = [
+        deposit(Tx(1), Amount::TEN),
+        timed(dispute(Tx(1)), "2026-09-01T00:00:00Z"),
+        resolve(Tx(1)),
+        partial(dispute, Tx(1), dec!(4)),
+        resolve(Tx(2)), // <- Fails, so it's not recorded
+        chargeback(Tx(1)),
+    ];
+
+    for txn in txns {
+        let _ = account.apply(txn);
+    }
+
+    assert_eq!(
+        account.dispute_history[&Tx(1)],
+        [
+            DisputeEvent {
+                status: DisputeStatus::Open,
+                amount: Amount::TEN,
+                timestamp: Some(at("2026-09-01T00:00:00Z")),
+            },
+            DisputeEvent {
+                status: DisputeStatus::Resolved,
+                amount: Amount::TEN,
+                timestamp: None,
+            },
+            DisputeEvent {
+                status: DisputeStatus::Reopened,
+                amount: amount(dec!(4)),
+                timestamp: None,
+            },
+            DisputeEvent {
+                status: DisputeStatus::ChargedBack,
+                amount: amount(dec!(4)),
+                timestamp: None,
+            },
+        ]
+    );
+    assert_eq!(account.dispute_counts.disputes, 2);
+    assert_eq!(account.dispute_counts.resolves, 1);
+    assert_eq!(account.dispute_counts.chargebacks, 1);
 }
 
+// Dispute expiry tests
+
 #[test]
-fn negative_balance_hold_insufficient_available_funds() {
-    let mut balance = Balance::default();
-    balance
-        .hold(Amount::ONE)
-        .expect_err("insufficient available funds");
+fn positive_ledger_expired_dispute_is_resolved() {
+    let mut ledger = expiring_ledger(ExpiryPolicy::Resolve);
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"),
+        timed(dispute(Tx(1)), "2026-09-02T00:00:00Z"),
+        timed(deposit(Tx(2), Amount::ONE), "2026-09-05T00:00:00Z"),
+    ];
+    for txn in &txns[..2] {
+        ledger.apply(*txn).expect("txns are valid");
+    }
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
+
+    ledger.apply(txns[2]).expect("deposit is valid");
+    let account = ledger.get_account_for(C1);
+    assert_eq!(account.balance.held(), Amount::ZERO);
+    assert_eq!(account.balance.available(), amount(dec!(11)));
+    assert_eq!(
+        ledger.synthetic,
+        [timed(resolve(Tx(1)), "2026-09-04T00:00:00Z")]
+    );
 }
 
 #[test]
-fn negative_balance_release_insufficient_funds_on_hold() {
-    let mut balance = Balance::default();
-    balance
-        .release(Amount::ONE)
-        .expect_err("insufficient funds on hold");
+fn positive_ledger_expired_dispute_is_charged_back() {
+    let mut ledger = expiring_ledger(ExpiryPolicy::Chargeback);
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"),
+        timed(dispute(Tx(1)), "2026-09-02T00:00:00Z"),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+
+    ledger.expire_disputes(at("2026-09-04T00:00:00Z"));
+    let account = ledger.get_account_for(C1);
+    assert_eq!(account.balance.total(), Amount::ZERO);
+    assert!(account.locked);
+    assert_eq!(
+        ledger.synthetic,
+        [timed(chargeback(Tx(1)), "2026-09-04T00:00:00Z")]
+    );
 }
 
 #[test]
-fn negative_balance_reimburse_insufficient_funds_on_hold() {
-    let mut balance = Balance::default();
-    balance
-        .reimburse(Amount::ONE)
-        .expect_err("insufficient funds on hold");
+fn negative_ledger_dispute_does_not_expire_before_deadline() {
+    let mut ledger = expiring_ledger(ExpiryPolicy::Resolve);
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"),
+        timed(dispute(Tx(1)), "2026-09-02T00:00:00Z"),
+        timed(resolve(Tx(1)), "2026-09-03T00:00:00Z"),
+        timed(dispute(Tx(1)), "2026-09-03T12:00:00Z"),
+        timed(deposit(Tx(2), Amount::ONE), "2026-09-05T00:00:00Z"),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+
+    // The first dispute was resolved on time, and the second one is still within its deadline
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
+    assert!(ledger.synthetic.is_empty());
 }
 
-// Ledger tests
+#[test]
+fn negative_ledger_undated_dispute_never_expires() {
+    let mut ledger = expiring_ledger(ExpiryPolicy::Resolve);
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"),
+        dispute(Tx(1)),
+        timed(deposit(Tx(2), Amount::ONE), "2027-09-01T00:00:00Z"),
+    ];
+    for txn in txns {
+        ledger.apply(txn).expect("txns are valid");
+    }
+
+    assert_eq!(ledger.get_account_for(C1).balance.held(), Amount::TEN);
+    assert!(ledger.synthetic.is_empty());
+}
+
+// Period report tests
 
 #[test]
-fn positive_ledger_routing() {
+fn positive_period_report_opening_balances() {
     let mut ledger = Ledger::default();
+    let mut report = PeriodReport::new(at("2026-09-01T00:00:00Z"));
+    let txns = [
+        timed(deposit(Tx(1), Amount::TEN), "2026-08-15T12:00:00Z"),
+        timed(deposit(Tx(2), Amount::TWO), "2026-09-02T12:00:00Z"),
+        timed(withdrawal(Tx(3), Amount::ONE), "2026-09-03T12:00:00Z"),
+    ];
+
+    for txn in txns {
+        report.apply(&mut ledger, txn).expect("all txns are valid");
+    }
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(report.opening(account).available(), Amount::TEN);
+    assert_eq!(account.balance.available(), amount(dec!(11)));
+}
 
+#[test]
+fn positive_period_report_starts_at_since() {
+    let mut ledger = Ledger::default();
+    let mut report = PeriodReport::new(at("2026-09-01T00:00:00Z"));
     let txns = [
-        Transaction {
-            t_type: Type::Deposit(Deposit {
-                amount: Amount::TEN,
-                dispute_status: DisputeStatus::default(),
-            }),
-            client: Client(1),
-            tx: Tx(1),
-        },
-        Transaction {
-            t_type: Type::Deposit(Deposit {
-                amount: Amount::TWO,
-                dispute_status: DisputeStatus::default(),
-            }),
-            client: Client(2),
-            tx: Tx(2),
-        },
+        timed(deposit(Tx(1), Amount::TEN), "2026-08-31T23:59:59Z"),
+        timed(deposit(Tx(2), Amount::TWO), "2026-09-01T00:00:00Z"),
     ];
 
-    txns.map(|txn| ledger.apply(txn).expect("both txns are valid"));
-    assert_eq!(
-        ledger.get_account_for(Client(1)).balance.available(),
-        Amount::TEN
-    );
-    assert_eq!(
-        ledger.get_account_for(Client(2)).balance.available(),
-        Amount::TWO
-    );
+    for txn in txns {
+        report.apply(&mut ledger, txn).expect("all txns are valid");
+    }
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(report.opening(account).available(), Amount::TEN);
+}
+
+#[test]
+fn positive_period_report_inactive_account_opens_with_current_balance() {
+    let mut ledger = Ledger::default();
+    let mut report = PeriodReport::new(at("2026-09-01T00:00:00Z"));
+    report
+        .apply(
+            &mut ledger,
+            timed(deposit(Tx(1), Amount::TEN), "2026-08-15T12:00:00Z"),
+        )
+        .expect("deposit is valid");
+
+    let account = ledger.get_account_for(C1);
+    assert_eq!(report.opening(account), account.balance);
 }
 
 // Account tests happy path
@@ -153,12 +1766,9 @@ fn positive_account_deposits_and_withdrawal() {
         deposit(Tx(2), Amount::TEN),
         withdrawal(Tx(3), Amount::TWO),
     ];
-    txns.map(|txn| account.apply(txn).expect("happy path shouldn't err"));
+    let _ = txns.map(|txn| account.apply(txn).expect("happy path shouldn't err"));
 
-    assert_eq!(
-        account.balance.available(),
-        Amount::TEN + Amount::TEN - Amount::TWO
-    );
+    assert_eq!(account.balance.available(), amount(dec!(18)));
 }
 
 #[test]
@@ -212,7 +1822,7 @@ fn positive_account_edge_case_open_dispute_after_previous_one_resolved() {
         chargeback(Tx(1)),
     ];
 
-    txns.map(|txn| {
+    let _ = txns.map(|txn| {
         account.apply(txn).expect("all transactions should succeed");
     });
     assert_eq!(account.balance.available(), Amount::ZERO);
@@ -221,6 +1831,20 @@ fn positive_account_edge_case_open_dispute_after_previous_one_resolved() {
 }
 
 // Account tests unhappy paths
+#[test]
+fn negative_account_overflowing_deposit_is_rejected() {
+    let mut account = default_account();
+    let txns = [deposit(Tx(1), Amount::MAX), deposit(Tx(2), Amount::ONE)];
+
+    account.apply(txns[0]).expect("the first deposit fits");
+    account
+        .apply(txns[1])
+        .expect_err("the second deposit would overflow the balance");
+
+    assert_eq!(account.balance.available(), Amount::MAX);
+    assert!(!account.deposits.contains_key(&Tx(2)));
+}
+
 #[test]
 fn negative_account_insufficient_funds() {
     let mut account = default_account();
@@ -257,13 +1881,63 @@ fn negative_account_rejects_transactions_when_locked() {
         chargeback(Tx(1)),
     ];
 
-    txns.map(|txn| {
+    let _ = txns.map(|txn| {
         account
             .apply(txn)
             .expect_err("a locked account should reject all transactions");
     });
 }
 
+#[test]
+fn negative_account_rejects_transactions_out_of_order() {
+    let mut account = default_account();
+    account
+        .apply(timed(deposit(Tx(1), Amount::TEN), "2026-09-02T00:00:00Z"))
+        .expect("first transaction is in order");
+    account
+        .apply(timed(deposit(Tx(2), Amount::TEN), "2026-09-02T00:00:00Z"))
+        .expect("equal timestamps are in order");
+    account
+        .apply(deposit(Tx(3), Amount::TEN))
+        .expect("undated transactions are not checked");
+
+    let e = account
+        .apply(timed(
+            withdrawal(Tx(4), Amount::ONE),
+            "2026-09-01T00:00:00Z",
+        ))
+        .expect_err("the withdrawal is earlier than the latest transaction");
+    assert_eq!(
+        e.downcast_ref::<NonMonotonicTimestampError>(),
+        Some(&NonMonotonicTimestampError {
+            tx: Tx(4),
+            timestamp: at("2026-09-01T00:00:00Z"),
+            last: at("2026-09-02T00:00:00Z"),
+        })
+    );
+    assert_eq!(account.balance.available(), amount(dec!(30)));
+}
+
+#[test]
+fn positive_account_rejected_transactions_do_not_advance_timestamp() {
+    let mut account = default_account();
+    account
+        .apply(timed(deposit(Tx(1), Amount::TEN), "2026-09-01T00:00:00Z"))
+        .expect("first transaction is in order");
+    account
+        .apply(timed(
+            withdrawal(Tx(2), amount(dec!(20))),
+            "2026-09-03T00:00:00Z",
+        ))
+        .expect_err("insufficient available funds");
+
+    account
+        .apply(timed(deposit(Tx(3), Amount::TEN), "2026-09-02T00:00:00Z"))
+        .expect("the rejected withdrawal doesn't count as the latest transaction");
+    assert_eq!(account.last_timestamp, Some(at("2026-09-02T00:00:00Z")));
+    assert_eq!(account.balance.available(), amount(dec!(20)));
+}
+
 #[test]
 fn negative_account_rejects_transactions_with_wrong_client() {
     let mut account = Account::new(Client(2)); //Different from the default
@@ -275,7 +1949,7 @@ fn negative_account_rejects_transactions_with_wrong_client() {
         chargeback(Tx(1)),
     ];
 
-    txns.map(|txn| {
+    let _ = txns.map(|txn| {
         account
             .apply(txn)
             .expect_err("an account shouldn't process transactions from other clients");
@@ -287,7 +1961,7 @@ fn negative_account_dispute_missing_deposit() {
     let mut account = default_account();
     let txns = [dispute(Tx(1))];
 
-    txns.map(|txn| {
+    let _ = txns.map(|txn| {
         account
             .apply(txn)
             .expect_err("can't process dispute on a deposit that doesn't exist");
@@ -327,7 +2001,7 @@ fn negative_account_dispute_insufficient_funds() {
             .apply(*txn)
             .expect("deposit and withdrawal shouldn't fail");
     });
-    assert_eq!(account.balance.available(), Amount::TEN - Amount::ONE);
+    assert_eq!(account.balance.available(), amount(dec!(9)));
 
     account
         .apply(txns[2])
@@ -380,7 +2054,142 @@ fn negative_account_edge_case_open_dispute_after_previous_one_chargedback() {
 
 // Helpers
 
+/// A notification received by a `Recorder`
+#[derive(Debug, PartialEq)]
+enum Observed {
+    Applied(Tx, Vec<BalanceChange>),
+    Rejected(Tx),
+    Transition(Tx, DisputeStatus, BalanceChange),
+    Locked(Tx),
+}
+
+/// Records the notifications it receives, so they can be inspected from outside the ledger
+#[derive(Debug)]
+struct Recorder(Arc<Mutex<Vec<Observed>>>);
+
+impl LedgerObserver for Recorder {
+    fn applied(&mut self, txn: &Transaction, changes: &[BalanceChange]) {
+        self.0
+            .lock()
+            .expect("no panic while recording")
+            .push(Observed::Applied(txn.tx, changes.to_vec()));
+    }
+
+    fn rejected(&mut self, txn: &Transaction, _error: &anyhow::Error) {
+        self.0
+            .lock()
+            .expect("no panic while recording")
+            .push(Observed::Rejected(txn.tx));
+    }
+
+    fn dispute_transition(
+        &mut self,
+        txn: &Transaction,
+        event: &DisputeEvent,
+        change: &BalanceChange,
+    ) {
+        self.0
+            .lock()
+            .expect("no panic while recording")
+            .push(Observed::Transition(txn.tx, event.status, *change));
+    }
+
+    fn locked(&mut self, txn: &Transaction, _change: &BalanceChange) {
+        self.0
+            .lock()
+            .expect("no panic while recording")
+            .push(Observed::Locked(txn.tx));
+    }
+}
+
+fn observed_ledger() -> (Ledger, Arc<Mutex<Vec<Observed>>>) {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let mut ledger = Ledger::default();
+    ledger
+        .observers
+        .push(Box::new(Recorder(Arc::clone(&events))));
+    (ledger, events)
+}
+
+fn balance(available: Amount, held: Amount) -> Balance {
+    let mut balance = Balance::default();
+    balance
+        .credit(available.checked_add(held).expect("no overflow"))
+        .expect("no overflow");
+    balance.hold(held).expect("enough available funds");
+    balance
+}
+
 const C1: Client = Client(1);
+const C2: Client = Client(2);
+const HOUSE: Client = Client(0);
+
+fn fee_ledger() -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.fee_schedule = Some(FeeSchedule {
+        house: HOUSE,
+        deposit: None,
+        withdrawal: Some(Fee {
+            min: Some(Amount::TWO),
+            ..Fee::default()
+        }),
+        transfer: Some(Fee {
+            flat: Amount::ONE,
+            ..Fee::default()
+        }),
+        dispute: None,
+        resolve: None,
+        chargeback: Some(Fee {
+            flat: amount(dec!(15)),
+            ..Fee::default()
+        }),
+    });
+    ledger
+}
+
+fn risky_account(limits: RiskLimits) -> Account {
+    let mut account = default_account();
+    account.risk_limits = Some(limits);
+    account
+        .apply(deposit(Tx(1), Amount::TEN))
+        .expect("deposit is valid");
+    account
+}
+
+fn expiring_ledger(policy: ExpiryPolicy) -> Ledger {
+    let mut ledger = Ledger::default();
+    ledger.dispute_expiry = Some(DisputeExpiry {
+        after: TimeDelta::days(2),
+        policy,
+    });
+    ledger
+}
+
+fn partial(full: fn(Tx) -> Transaction, tx: Tx, d: Decimal) -> Transaction {
+    let a = Some(d);
+    let t_type = match full(tx).t_type {
+        Type::Dispute(_) => Type::Dispute(a),
+        Type::Resolve(_) => Type::Resolve(a),
+        Type::Chargeback(_) => Type::Chargeback(a),
+        t_type => t_type,
+    };
+    Transaction { t_type, ..full(tx) }
+}
+
+fn at(timestamp: &str) -> Timestamp {
+    timestamp.parse().expect("valid RFC 3339 constant")
+}
+
+fn timed(txn: Transaction, timestamp: &str) -> Transaction {
+    Transaction {
+        timestamp: Some(at(timestamp)),
+        ..txn
+    }
+}
+
+fn amount(d: Decimal) -> Amount {
+    Amount::try_from(d).expect("non-negative constant")
+}
 
 fn default_account() -> Account {
     Account::new(Client(1))
@@ -388,12 +2197,10 @@ fn default_account() -> Account {
 
 fn deposit(tx: Tx, a: Amount) -> Transaction {
     Transaction {
-        t_type: Type::Deposit(Deposit {
-            amount: a,
-            dispute_status: DisputeStatus::default(),
-        }),
+        t_type: Type::Deposit(Deposit::new(a)),
         client: C1,
         tx,
+        timestamp: None,
     }
 }
 
@@ -402,29 +2209,42 @@ fn withdrawal(tx: Tx, a: Amount) -> Transaction {
         t_type: Type::Withdrawal(Withdrawal { amount: a }),
         client: C1,
         tx,
+        timestamp: None,
     }
 }
 
 fn dispute(tx: Tx) -> Transaction {
     Transaction {
-        t_type: Type::Dispute,
+        t_type: Type::Dispute(None),
         client: C1,
         tx,
+        timestamp: None,
     }
 }
 
 fn resolve(tx: Tx) -> Transaction {
     Transaction {
-        t_type: Type::Resolve,
+        t_type: Type::Resolve(None),
         client: C1,
         tx,
+        timestamp: None,
     }
 }
 
 fn chargeback(tx: Tx) -> Transaction {
     Transaction {
-        t_type: Type::Chargeback,
+        t_type: Type::Chargeback(None),
+        client: C1,
+        tx,
+        timestamp: None,
+    }
+}
+
+fn transfer(tx: Tx, to: Client, a: Amount) -> Transaction {
+    Transaction {
+        t_type: Type::Transfer(Transfer { to, amount: a }),
         client: C1,
         tx,
+        timestamp: None,
     }
 }
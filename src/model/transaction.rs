@@ -1,9 +1,11 @@
 use anyhow::{Result, bail};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
 
-use super::common::{Amount, Client, Tx};
+use super::common::{Amount, Client, Timestamp, Tx};
 
 /// A transaction
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Transaction {
     /// The type of the transaction
     pub t_type: Type,
@@ -11,74 +13,209 @@ pub struct Transaction {
     pub client: Client,
     /// The transaction id
     pub tx: Tx,
+    /// The instant the transaction happened at, if known
+    pub timestamp: Option<Timestamp>,
 }
 
 /// The type of a `Transaction`.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub enum Type {
     /// A deposit
     Deposit(Deposit),
     /// A withdrawal
     Withdrawal(Withdrawal),
-    /// A dispute
-    Dispute,
-    /// A resolve
-    Resolve,
-    /// A chargeback
-    Chargeback,
+    /// A dispute, of part of the deposit if an amount is provided, or of all of it otherwise
+    ///
+    /// The amounts of the dispute family are only checked when applied, as they are dropped
+    /// unless partial disputes are enabled.
+    Dispute(Option<Decimal>),
+    /// A resolve, of part of the disputed amount if provided, or of all of it otherwise
+    Resolve(Option<Decimal>),
+    /// A chargeback, of part of the disputed amount if provided, or of all of it otherwise
+    Chargeback(Option<Decimal>),
+    /// A transfer from the transaction's client to another one
+    Transfer(Transfer),
 }
 
 /// Parameters for a deposit
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Deposit {
     /// The amount to deposit
     pub amount: Amount,
     /// A dispute status
     pub dispute_status: DisputeStatus,
+    /// The amount currently disputed, and thus on hold
+    pub disputed: Amount,
+    /// The amount already charged back
+    pub charged_back: Amount,
 }
 
 /// Parameters for a withdrawal
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
 pub struct Withdrawal {
     /// The amount to withdraw
     pub amount: Amount,
 }
 
-/// The dispute status of a deposit
+/// Parameters for a transfer
+///
+/// Transfers can't be disputed, neither by the sender nor by the recipient.
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct Transfer {
+    /// The client receiving the funds
+    pub to: Client,
+    /// The amount to transfer
+    pub amount: Amount,
+}
+
+/// The dispute lifecycle status of a deposit
 ///
-/// `Closed` the default status, no dispute is currently pending resolution
-/// `Opened` a dispute is pending resolution
-#[derive(Debug, PartialEq, Default, Clone, Copy)]
+/// `None` the default status, the deposit was never disputed
+/// `Open` a dispute is pending resolution
+/// `Resolved` the latest dispute was resolved, the deposit can be disputed again
+/// `Reopened` a dispute is pending resolution, after a previous one was resolved
+/// `ChargedBack` the latest dispute was charged back, the deposit can't be disputed again
+#[derive(Debug, PartialEq, Eq, Default, Clone, Copy, Serialize, Deserialize)]
 pub enum DisputeStatus {
     #[default]
-    Closed,
-    Opened,
+    None,
+    Open,
+    Resolved,
+    Reopened,
+    ChargedBack,
+}
+
+/// A transition in the dispute lifecycle of a deposit
+#[derive(Debug, PartialEq, Clone, Copy, Serialize, Deserialize)]
+pub struct DisputeEvent {
+    /// The status of the deposit after the transition
+    pub status: DisputeStatus,
+    /// The amount disputed, resolved or charged back by the transition
+    pub amount: Amount,
+    /// The instant the transition happened at, if known
+    pub timestamp: Option<Timestamp>,
+}
+
+impl Transaction {
+    /// Drop the amounts of a dispute-family transaction
+    ///
+    /// Turns partial disputes, resolves and chargebacks into full ones. Any other transaction is
+    /// returned as is.
+    #[must_use]
+    pub fn without_dispute_amount(self) -> Self {
+        let t_type = match self.t_type {
+            Type::Dispute(_) => Type::Dispute(None),
+            Type::Resolve(_) => Type::Resolve(None),
+            Type::Chargeback(_) => Type::Chargeback(None),
+            t_type => t_type,
+        };
+        Transaction { t_type, ..self }
+    }
 }
 
 impl Deposit {
+    /// Create an undisputed deposit of `amount`
+    #[must_use]
+    pub fn new(amount: Amount) -> Self {
+        Self {
+            amount,
+            dispute_status: DisputeStatus::default(),
+            disputed: Amount::ZERO,
+            charged_back: Amount::ZERO,
+        }
+    }
+
+    /// Whether this deposit has a dispute pending resolution
+    #[must_use]
+    pub fn is_disputed(&self) -> bool {
+        matches!(
+            self.dispute_status,
+            DisputeStatus::Open | DisputeStatus::Reopened
+        )
+    }
+
     /// Opens a dispute on this deposit
     ///
+    /// Disputes `amount`, or everything that's left to dispute if `None`. A deposit can be
+    /// disputed partially several times, as long as the disputed amount does not exceed the
+    /// deposit's amount. Returns the amount newly disputed, which should be put on hold.
+    ///
+    /// A resolved deposit is `Reopened` by a new dispute, while a charged back deposit can't be
+    /// disputed ever again.
+    ///
     /// # Errors
     ///
-    /// Returns `anyhow::Error` if a dispute is already opened
-    pub fn open_dispute(&mut self) -> Result<()> {
-        if let DisputeStatus::Opened = self.dispute_status {
-            bail!("can't open a new dispute until the previous one is finalized");
+    /// Returns `anyhow::Error` if the deposit was charged back, if there's nothing left to
+    /// dispute, or if `amount` exceeds it.
+    pub fn open_dispute(&mut self, amount: Option<Amount>) -> Result<Amount> {
+        let status = match self.dispute_status {
+            DisputeStatus::None => DisputeStatus::Open,
+            DisputeStatus::Resolved => DisputeStatus::Reopened,
+            DisputeStatus::Open | DisputeStatus::Reopened => self.dispute_status,
+            DisputeStatus::ChargedBack => bail!("can't dispute a charged back deposit"),
+        };
+        let disputable = self
+            .amount
+            .checked_sub(self.disputed)
+            .and_then(|a| a.checked_sub(self.charged_back))?;
+        let amount = amount.unwrap_or(disputable);
+        if disputable == Amount::ZERO {
+            bail!("can't open a new dispute, the whole deposit is already disputed");
+        }
+        if amount == Amount::ZERO || amount > disputable {
+            bail!("can't dispute {amount:?}, the undisputed amount is {disputable:?}");
         }
-        self.dispute_status = DisputeStatus::Opened;
-        Ok(())
+
+        self.disputed = self.disputed.checked_add(amount)?;
+        self.dispute_status = status;
+        Ok(amount)
     }
 
-    /// Closes a dispute on this deposit
+    /// Resolves a dispute on this deposit
+    ///
+    /// Resolves `amount` of the disputed amount, or all of it if `None`. The dispute is `Resolved`
+    /// once nothing is left disputed. Returns the amount resolved, which should be released.
     ///
     /// # Errors
     ///
-    /// Returns `anyhow::Error` unless there is an existing opened dispute
-    pub fn close_dispute(&mut self) -> Result<()> {
-        if let DisputeStatus::Closed = self.dispute_status {
+    /// Returns `anyhow::Error` unless there is an existing opened dispute of at least `amount`
+    pub fn resolve_dispute(&mut self, amount: Option<Amount>) -> Result<Amount> {
+        let amount = self.disputed_portion(amount)?;
+
+        self.disputed = self.disputed.checked_sub(amount)?;
+        if self.disputed == Amount::ZERO {
+            self.dispute_status = DisputeStatus::Resolved;
+        }
+        Ok(amount)
+    }
+
+    /// Charges back a dispute on this deposit
+    ///
+    /// Charges back `amount` of the disputed amount, or all of it if `None`, and closes the
+    /// dispute as `ChargedBack`. Returns the amount charged back, which should be reimbursed, and
+    /// the rest of the disputed amount, which should be released.
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` unless there is an existing opened dispute of at least `amount`
+    pub fn charge_back(&mut self, amount: Option<Amount>) -> Result<(Amount, Amount)> {
+        let amount = self.disputed_portion(amount)?;
+        let released = self.disputed.checked_sub(amount)?;
+
+        self.charged_back = self.charged_back.checked_add(amount)?;
+        self.disputed = Amount::ZERO;
+        self.dispute_status = DisputeStatus::ChargedBack;
+        Ok((amount, released))
+    }
+
+    fn disputed_portion(&self, amount: Option<Amount>) -> Result<Amount> {
+        if !self.is_disputed() {
             bail!("can't close unless there is an existing dispute");
         }
-        self.dispute_status = DisputeStatus::Closed;
-        Ok(())
+        let amount = amount.unwrap_or(self.disputed);
+        if amount > self.disputed {
+            bail!("can't close more than the disputed amount");
+        }
+        Ok(amount)
     }
 }
@@ -0,0 +1,141 @@
+use std::{collections::HashMap, fmt::Debug};
+
+use anyhow::Result;
+use chrono::TimeDelta;
+use serde_json::Value;
+use thiserror::Error;
+
+use super::{
+    account::Account,
+    common::{Amount, Client, Timestamp, Tx},
+    transaction::{Transaction, Type},
+};
+
+/// A custom check on the transactions applied by a `Ledger`
+///
+/// Validators are registered on `Ledger::validators`, and consulted in order for every
+/// transaction. They can keep their own state across transactions, in which case they should
+/// `save` and `restore` it, so a ledger resumed from a checkpoint validates as it would have.
+pub trait TransactionValidator: Debug + Send {
+    /// Check a transaction before it's applied to the `account` it belongs to
+    ///
+    /// The transaction is rejected with a `RejectedError` as soon as a validator rejects it.
+    fn before(&mut self, _account: &Account, _txn: &Transaction) -> Verdict {
+        Verdict::Accept
+    }
+
+    /// Check a transaction once it has been applied to the `account` it belongs to
+    ///
+    /// The transaction can't be rejected anymore, so it can only be flagged with a reason.
+    fn after(&mut self, _account: &Account, _txn: &Transaction) -> Option<String> {
+        None
+    }
+
+    /// Save the state kept across transactions, to checkpoint it along with the ledger
+    ///
+    /// Stateless validators have nothing to save.
+    fn save(&self) -> Value {
+        Value::Null
+    }
+
+    /// Restore the state saved by `save`
+    ///
+    /// # Errors
+    ///
+    /// Returns `anyhow::Error` if the state is not one this validator saves.
+    fn restore(&mut self, _state: Value) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The outcome of a validator's check
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum Verdict {
+    /// The transaction can be applied
+    Accept,
+    /// The transaction must not be applied, for a reason
+    Reject(String),
+    /// The transaction can be applied, but is flagged for a reason
+    Flag(String),
+}
+
+/// A transaction flagged by a validator
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Flag {
+    pub client: Client,
+    pub tx: Tx,
+    pub reason: String,
+}
+
+/// A transaction was rejected by a validator.
+#[derive(Error, Debug, PartialEq, Eq)]
+#[error("{tx:?} rejected: {reason}")]
+pub struct RejectedError {
+    pub tx: Tx,
+    pub reason: String,
+}
+
+/// Rejects withdrawals and transfers from newly opened accounts
+///
+/// An account is considered opened at its client's first timestamped transaction. Undated
+/// transactions can't be placed in time, so they are never rejected.
+#[derive(Debug)]
+pub struct NewAccountOutflows {
+    /// How long after being opened an account can't withdraw nor transfer funds
+    pub min_age: TimeDelta,
+    opened: HashMap<Client, Timestamp>,
+}
+
+impl NewAccountOutflows {
+    #[must_use]
+    pub fn new(min_age: TimeDelta) -> Self {
+        Self {
+            min_age,
+            opened: HashMap::new(),
+        }
+    }
+}
+
+impl TransactionValidator for NewAccountOutflows {
+    fn before(&mut self, _account: &Account, txn: &Transaction) -> Verdict {
+        let Some(now) = txn.timestamp else {
+            return Verdict::Accept;
+        };
+        let opened = *self.opened.entry(txn.client).or_insert(now);
+        match txn.t_type {
+            Type::Withdrawal(_) | Type::Transfer(_)
+                if now.0.signed_duration_since(opened.0) < self.min_age =>
+            {
+                Verdict::Reject(format!("account opened at {opened} is too new"))
+            }
+            _ => Verdict::Accept,
+        }
+    }
+
+    /// Save when each account was opened.
+    fn save(&self) -> Value {
+        serde_json::to_value(&self.opened).unwrap_or_default()
+    }
+
+    fn restore(&mut self, state: Value) -> Result<()> {
+        self.opened = serde_json::from_value(state)?;
+        Ok(())
+    }
+}
+
+/// Flags deposits of a round amount, i.e. an exact multiple of `multiple`
+#[derive(Debug)]
+pub struct RoundDeposits {
+    pub multiple: Amount,
+}
+
+impl TransactionValidator for RoundDeposits {
+    fn after(&mut self, _account: &Account, txn: &Transaction) -> Option<String> {
+        match txn.t_type {
+            Type::Deposit(deposit) if deposit.amount.is_multiple_of(self.multiple) => {
+                Some(format!("round deposit, a multiple of {}", self.multiple))
+            }
+            _ => None,
+        }
+    }
+}
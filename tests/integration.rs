@@ -1,7 +1,8 @@
 use std::{
     fs::File,
     io::{BufReader, Read},
-    path::PathBuf,
+    path::{Path, PathBuf},
+    process::Command,
 };
 
 use rust_decimal_macros::dec;
@@ -9,12 +10,12 @@ use rust_decimal_macros::dec;
 use little_ledger::{
     io::{
         input::{create_csv_reader, deserialize_transactions},
-        output::{create_csv_writer, serialize_ledger},
+        output::{OutputColumns, create_csv_writer, serialize_ledger},
     },
     model::{
         common::{Amount, Client, Tx},
         ledger::Ledger,
-        transaction::{Deposit, DisputeStatus, Transaction, Type, Withdrawal},
+        transaction::{Deposit, Transaction, Type, Withdrawal},
     },
 };
 
@@ -31,12 +32,12 @@ fn deserialize_apply_serialize() {
     assert_eq!(
         txns[0],
         Transaction {
-            t_type: Type::Deposit(Deposit {
-                amount: Amount::try_from(dec!(1.2345)).expect("non-negative constant"),
-                dispute_status: DisputeStatus::default(),
-            }),
+            t_type: Type::Deposit(Deposit::new(
+                Amount::try_from(dec!(1.2345)).expect("non-negative constant")
+            )),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
         }
     );
     assert_eq!(
@@ -46,50 +47,57 @@ fn deserialize_apply_serialize() {
                 amount: Amount::ZERO,
             }),
             client: Client(1),
-            tx: Tx(2)
+            tx: Tx(2),
+            timestamp: None,
         }
     );
     assert_eq!(
         txns[2],
         Transaction {
-            t_type: Type::Dispute,
+            t_type: Type::Dispute(None),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
         }
     );
     assert_eq!(
         txns[3],
         Transaction {
-            t_type: Type::Resolve,
+            t_type: Type::Resolve(None),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
         }
     );
     assert_eq!(
         txns[4],
         Transaction {
-            t_type: Type::Dispute,
+            t_type: Type::Dispute(None),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
         }
     );
     assert_eq!(
         txns[5],
         Transaction {
-            t_type: Type::Chargeback,
+            t_type: Type::Chargeback(Some(dec!(1.000))),
             client: Client(1),
-            tx: Tx(1)
+            tx: Tx(1),
+            timestamp: None,
         }
     );
 
     let mut ledger = Ledger::default();
-    txns.iter()
-        .for_each(|txn| ledger.apply(*txn).expect("transactions are valid"));
+    for txn in txns {
+        ledger.apply(txn).expect("transactions are valid");
+    }
 
     let file_writer =
         create_csv_writer(Some(PathBuf::from("./tests/output.csv"))).expect("should be readable");
 
-    serialize_ledger(ledger, Some(file_writer), true).expect("buffer should flush");
+    serialize_ledger(ledger, Some(file_writer), OutputColumns::default(), true)
+        .expect("buffer should flush");
 
     let file = File::open("./tests/output.csv").expect("file should open");
     let output_buf = BufReader::new(file);
@@ -104,3 +112,57 @@ fn deserialize_apply_serialize() {
     );
     assert!(output.contains("4,10000,0,10000,false"));
 }
+
+#[test]
+fn diff_exit_codes() {
+    let diff = |old: &Path, new: &Path| {
+        Command::new(env!("CARGO_BIN_EXE_little_ledger"))
+            .arg("diff")
+            .args([old, new])
+            .output()
+            .expect("should run")
+            .status
+            .code()
+    };
+    let dir = std::env::temp_dir();
+    let old = dir.join(format!("little_ledger_{}_old.csv", std::process::id()));
+    let new = dir.join(format!("little_ledger_{}_new.csv", std::process::id()));
+    std::fs::write(&old, "client,available,held,total,locked\n1,1,0,1,false\n")
+        .expect("writable temp file");
+    std::fs::write(&new, "client,available,held,total,locked\n1,2,0,2,false\n")
+        .expect("writable temp file");
+
+    assert_eq!(diff(&old, &old), Some(0));
+    assert_eq!(diff(&old, &new), Some(1));
+    assert_eq!(diff(&old, Path::new("./tests/input.csv")), Some(2)); // <- Not a ledger output
+
+    std::fs::remove_file(old).expect("removable temp file");
+    std::fs::remove_file(new).expect("removable temp file");
+}
+
+#[test]
+fn disputes_expire_at_the_end_of_the_input() {
+    let input =
+        std::env::temp_dir().join(format!("little_ledger_{}_expiry.csv", std::process::id()));
+    std::fs::write(
+        &input,
+        "type,client,tx,amount,timestamp\n\
+         deposit,1,1,10,2026-01-01T00:00:00Z\n\
+         dispute,1,1,,2026-01-02T00:00:00Z\n\
+         deposit,2,2,5,2026-03-01T00:00:00Z\n",
+    )
+    .expect("writable temp file");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_little_ledger"))
+        .arg(&input)
+        .args(["--dispute-expiry", "45days"])
+        .output()
+        .expect("should run");
+    let output = String::from_utf8(output.stdout).expect("output is UTF-8");
+
+    // The dispute is the last transaction of client 1, but client 2 is past its expiry
+    assert!(output.contains("1,10,0,10,false"));
+    assert!(output.contains("2,5,0,5,false"));
+
+    std::fs::remove_file(input).expect("removable temp file");
+}